@@ -1,14 +1,15 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::ToTokens;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use std::collections::HashMap;
 use syn::parse::{Error as SynError, Parse, ParseStream, Result as SynResult};
 use syn::parse_quote::ParseQuote;
+use syn::spanned::Spanned;
 
 use syn::{
     braced, bracketed, punctuated::Punctuated, token, Error, Expr as SynExpr, Expr, ExprCall,
-    ExprLit, ExprPath, Ident, LitStr,
+    ExprLit, ExprPath, Ident, Lit, LitStr,
 };
 
 pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
@@ -59,248 +60,195 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
         },
     );
 
-    let (middleware_req, middleware_res) =
-        config.middleware.map_or(Default::default(), |middleware| {
-            let mut middleware_req = vec![];
-            let mut middleware_res = vec![];
-            let mut i = 0u16;
-
-            middleware.request.map(|rm| {
-               rm.iter().for_each(|e| {
-                    let m_arg_ident = format_ident!("m_arg_{}", i);
-
-                    let (name, m_args) = match e {
-                        Func::Call(expr_call) => {
-                            let m_args: Vec<proc_macro2::TokenStream> = expr_call.args.iter().map(|arg| {
-                                if let SynExpr::Call(expr_call) = arg {
-                                    if expr_call.func.to_token_stream().to_string() == "request" {
-                                        let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
-                                        let i_ident = format_ident!("m_arg_{}", index);
-                                        return quote!{#i_ident.clone()};
-                                    }
-                                }
-                                quote! {#arg}
-                            }).collect();
-
-                            let q = if m_args.len() > 1 {
-                                quote! {(#(#m_args ,)*)}
-                            } else if m_args.len() == 1 {
-                                quote! {#(#m_args ,)*}
-                            } else {
-                                quote! {()}
-                            };
-
-                            (expr_call.func.to_token_stream(), q)
-                        },
-                        Func::Path(expr_path) => {
-                            (expr_path.to_token_stream(), quote! {()})
-                        }
-                    };
-
-
-                    middleware_req.push(quote! {
-                    let #m_arg_ident = match #name::call(&mut r, inner_module.clone(), #m_args).await {
-                        Ok(k) => k,
-                        Err(e) => return Ok(e.respond_err()),
-                    };
-                });
-                    i += 1;
-                });
-            });
-
-            let mut i = 0u16;
-
-            middleware.response.map(|ref mut rm| {
-                rm.iter_mut().for_each(|e| {
-                    let r_m_arg_ident = format_ident!("res_m_arg_{}", i);
-                    let (name, m_args) = match e {
-                        Func::Call(expr_call) => {
-                            let m_args: Vec<proc_macro2::TokenStream> = expr_call.args.iter_mut().map(|arg| {
-                                if let SynExpr::Call(expr_call) = arg {
-                                    if expr_call.func.to_token_stream().to_string() == "request" {
-                                        let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
-                                        let i_ident = format_ident!("m_arg_{}", index);
-                                        return quote!{#i_ident.clone()};
-                                    }
-                                    if expr_call.func.to_token_stream().to_string() == "response" {
-                                        let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
-                                        let r_m_arg_ident = format_ident!("res_m_arg_{}", index);
-                                        return quote!{#r_m_arg_ident.clone()};
-                                    }
-                                }
-                                if  let SynExpr::Tuple(tuple) = arg.clone() {
-                                    let tuple_expr: Vec<proc_macro2::TokenStream> = tuple.elems.iter().map(|tuple_arg| {
-                                        if let SynExpr::Call(expr_call) = tuple_arg {
-                                            if expr_call.func.to_token_stream().to_string() == "request" {
-                                                let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
-                                                let i_ident = format_ident!("m_arg_{}", index);
-                                                return quote!{#i_ident.clone()};
-                                            }
-                                            if expr_call.func.to_token_stream().to_string() == "response" {
-                                                let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
-                                                let r_m_arg_ident = format_ident!("res_m_arg_{}", index);
-                                                return quote!{#r_m_arg_ident.clone()};
-                                            }
-                                        }
-                                        quote! {#tuple_arg}
-                                    }).collect();
-                                    return quote! {( #(#tuple_expr ,)* )};
-                                }
-                                quote! {#arg}
-                            }).collect();
-
-                            let q = if m_args.len() > 1 {
-                                quote! {(#(#m_args ,)*)}
-                            } else if m_args.len() == 1 {
-                                quote! {#(#m_args ,)*}
-                            } else {
-                                quote! {()}
-                            };
-
-                            (expr_call.func.to_token_stream(), q)
-
-                        },
-                        Func::Path(expr_path) => {
-                            (expr_path.to_token_stream(), quote! {()})
-                        }
-                    };
-
-                    middleware_res.push(quote! {
-                    let #r_m_arg_ident = match #name::call(&mut rb, inner_module.clone(), #m_args).await {
-                        Ok(k) => k,
-                        Err(e) => return Ok(e.respond_err()),
-                    };
-                });
-                    i += 1;
-                });
-            });
-
+    let (tls_def, tls_let, tls_self, server) = config.tls.map_or(
+        {
             (
-                middleware_req,
-                middleware_res,
+                quote! {},
+                quote! {},
+                quote! {},
+                quote! {let server = darpi::Server::bind(&address).serve(make_svc);},
             )
-        });
+        },
+        |tls| {
+            let tls_let = match tls {
+                Tls::Files {
+                    cert,
+                    key,
+                    client_ca,
+                    client_auth,
+                    reload,
+                } => {
+                    let client_ca = client_ca.map(|ca| quote! {.client_ca(#ca)});
+                    let client_auth = client_auth.map(|ca| {
+                        let optional = ca == "optional";
+                        quote! {.client_auth_optional(#optional)}
+                    });
+                    let reload = reload
+                        .map(|r| quote! {.reload_interval(std::time::Duration::from_secs(#r))});
+                    quote! {
+                        let tls = darpi::tls::TlsConfig::new(#cert, #key)
+                            #client_ca
+                            #client_auth
+                            #reload
+                            .build()
+                            .unwrap_or_else(|e| panic!("invalid tls configuration: {}", e));
+                    }
+                }
+                Tls::Config(config) => quote! {
+                    let tls = darpi::tls::Tls::from_server_config(#config);
+                },
+            };
 
-    let (jobs_req, jobs_res) = config.jobs.map_or(Default::default(), |jobs| {
-        let mut jobs_req = vec![];
-        let mut jobs_res = vec![];
+            (
+                quote! {tls: darpi::tls::Tls,},
+                tls_let,
+                quote! {tls: tls,},
+                quote! {
+                    let incoming = self.tls.bind(&address).await
+                        .unwrap_or_else(|e| panic!("could not bind `{}`: {}", address, e));
+                    let server = darpi::Server::builder(incoming).serve(make_svc);
+                },
+            )
+        },
+    );
 
-        jobs.request.map(|jr| {
-            jr.iter().for_each(|e| {
-                let (name, m_args) = match e {
-                    Func::Call(ec) => {
-                        let name = ec.func.to_token_stream();
-                        let m_args: Vec<proc_macro2::TokenStream> = ec
-                            .args
-                            .iter()
-                            .map(|arg| {
-                                quote! {#arg}
-                            })
-                            .collect();
+    let (app_req_names, app_res_names) = middleware_names(&config.middleware);
+    let (middleware_req, middleware_res) = make_middleware(config.middleware, "");
+    let (jobs_req, jobs_res) = make_jobs(config.jobs);
+
+    let mut scope_names = vec![];
+    let scopes: Vec<_> = config
+        .scopes
+        .into_iter()
+        .enumerate()
+        .map(|(i, scope)| {
+            scope_names.push(middleware_names(&scope.middleware));
+            let (middleware_req, middleware_res) =
+                make_middleware(scope.middleware, &format!("scope{}_", i));
+            let (jobs_req, jobs_res) = make_jobs(scope.jobs);
+            (middleware_req, middleware_res, jobs_req, jobs_res)
+        })
+        .collect();
+
+    let routing = config.routing.unwrap_or_default();
 
-                        let q = if m_args.len() > 1 {
-                            quote! {(#(#m_args ,)*)}
-                        } else if m_args.len() == 1 {
-                            quote! {#(#m_args ,)*}
-                        } else {
-                            quote! {()}
-                        };
+    let mut route_defs = vec![];
+    let mut route_strs = vec![];
+    let mut route_names = vec![];
+    let mut route_infos = vec![];
+    let mut route_match = vec![];
+    let mut groups: Vec<(String, Vec<usize>)> = vec![];
 
-                        (name, q)
+    // handlers bound to the same path share a single router entry
+    // so the router can tell an unknown path from an unsupported method
+    for (i, h) in handlers.iter().enumerate() {
+        if let Some(name) = &h.name {
+            let route = &h.route;
+            let ha = &h.handler;
+            let url_fn = format_ident!("{}_url", name.value());
+            // `#[derive(Path)]` defines a `route_arg_{field}` constant per field
+            // so a route argument without a matching field does not compile
+            let arg_checks: Vec<_> = route_arg_names(&route.to_token_stream().to_string())
+                .into_iter()
+                .map(|arg| {
+                    let check = format_ident!("route_arg_{}", arg, span = route.span());
+                    quote_spanned! {route.span()=>
+                        let _ = <<#ha as darpi::request::RoutePath>::Path>::#check;
                     }
-                    Func::Path(path) => (path.to_token_stream(), quote! {()}),
-                };
-
-                jobs_req.push(quote! {
-                    match #name::call(&r, inner_module.clone(), #m_args).await.into() {
-                        darpi::job::Job::CpuBound(function) => {
-                                let res = darpi::spawn(function);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue CpuBound job err: {}", e);
-                                }
-                            }
-                            darpi::job::Job::IOBlocking(function) => {
-                                let res = darpi::spawn(function);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue IOBlocking job err: {}", e);
-                                }
-                            }
-                            darpi::job::Job::Future(fut) => {
-                                let res = darpi::spawn(fut);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue Future job err: {}", e);
-                                }
-                            }
-                    };
-                });
+                })
+                .collect();
+            route_names.push(quote! {
+                // the handler is defined outside of the block `app!` expands to
+                #[allow(non_local_definitions)]
+                impl #ha {
+                    #[allow(dead_code)]
+                    pub fn #url_fn(args: &<#ha as darpi::request::RoutePath>::Path) -> String {
+                        #(#arg_checks )*
+                        darpi::request::route_path(#route, args)
+                    }
+                }
             });
-        });
-
-        jobs.response.map(|ref mut jr| {
-            jr.iter_mut().for_each(|e| {
-                let (name, m_args) = match e {
-                    Func::Call(ec) => {
-                        let name = ec.func.to_token_stream();
-                        let m_args: Vec<proc_macro2::TokenStream> = ec
-                            .args
-                            .iter()
-                            .map(|arg| {
-                                quote! {#arg}
-                            })
-                            .collect();
+        }
+        route_infos.push(make_route_info(
+            h,
+            &app_req_names,
+            &app_res_names,
+            &scope_names,
+        ));
+
+        let pattern = normalize_route(&h.route.to_token_stream().to_string());
+        match groups.iter_mut().find(|(p, _)| *p == pattern) {
+            Some((_, group)) => group.push(i),
+            None => {
+                route_strs.push(h.route.clone());
+                groups.push((pattern, vec![i]));
+            }
+        }
+    }
 
-                        let q = if m_args.len() > 1 {
-                            quote! {(#(#m_args ,)*)}
-                        } else if m_args.len() == 1 {
-                            quote! {#(#m_args ,)*}
-                        } else {
-                            quote! {()}
-                        };
-                        (name, q)
-                    }
-                    Func::Path(p) => (p.to_token_stream(), quote! {()}),
-                };
+    for (gi, (_, group)) in groups.iter().enumerate() {
+        let mut method_matches = vec![];
+        let mut allowed = vec![];
+        let mut get_route = None;
+
+        for i in group {
+            let h = &handlers[*i];
+            let id = format_ident!("route{}", i);
+            let ha = h.handler.clone();
+            let method = h.method.path.segments.last().unwrap().ident.to_string();
+
+            // scope middleware wraps the handler, the outermost scope runs first
+            let mut scope_req = vec![];
+            let mut scope_res = vec![];
+            for si in h.scopes.iter() {
+                let (middleware_req, _, jobs_req, _) = &scopes[*si];
+                scope_req.push(quote! {#(#middleware_req )* #(#jobs_req )*});
+            }
+            for si in h.scopes.iter().rev() {
+                let (_, middleware_res, _, jobs_res) = &scopes[*si];
+                scope_res.push(quote! {#(#middleware_res )* #(#jobs_res )*});
+            }
 
-                jobs_res.push(quote! {
-                    match #name::call(&rb, inner_module.clone(), #m_args).await.into() {
-                        darpi::job::Job::CpuBound(function) => {
-                                let res = darpi::spawn(function);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue CpuBound job err: {}", e);
-                                }
-                            }
-                            darpi::job::Job::IOBlocking(function) => {
-                                let res = darpi::spawn(function);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue IOBlocking job err: {}", e);
-                                }
-                            }
-                            darpi::job::Job::Future(fut) => {
-                                let res = darpi::spawn(fut);
-                                if let Err(e) = res {
-                                    log::warn!("could not queue Future job err: {}", e);
-                                }
-                            }
+            method_matches.push(quote! {
+                if #id::is_match(method.as_str()) {
+                    #(#scope_req )*
+                    let args_vec = rm.get_args().to_vec();
+                    let args = darpi::Args{
+                        request: r,
+                        container: inner_module.clone(),
+                        route_args: #id::get_tuple_args(&route_str, &args_vec),
                     };
-                });
+                    let mut rb = Handler::call(#ha, args).await.unwrap();
+                    #(#scope_res )*
+                    #(#middleware_res )*
+                    #(#jobs_res )*
+                    return Ok::<_, std::convert::Infallible>(rb);
+                }
             });
-        });
-
-        (jobs_req, jobs_res)
-    });
 
-    let mut route_defs = vec![];
-    let mut route_strs = vec![];
-    let mut route_match = vec![];
+            let r = make_route_lit(
+                &id,
+                &method,
+                h.route.to_token_stream(),
+                routing.percent_decode,
+            )?;
+            route_defs.push(r);
 
-    for (i, h) in handlers.iter().enumerate() {
-        let id = format_ident!("route{}", i);
-        route_strs.push(h.route.clone());
-        let ha = h.handler.clone();
+            if method == http::Method::GET.as_str() {
+                get_route = Some((id, ha, scope_req, scope_res));
+            }
+            if !allowed.contains(&method) {
+                allowed.push(method);
+            }
+        }
 
-        route_match.push(quote! {
-            #i => {
-                 if #id::is_match(method.as_str()) {
+        // HEAD is served by the GET handler unless it is registered explicitly
+        let head = http::Method::HEAD.to_string();
+        if let (Some((id, ha, scope_req, scope_res)), false) = (get_route, allowed.contains(&head))
+        {
+            method_matches.push(quote! {
+                if method == darpi::Method::HEAD {
+                    #(#scope_req )*
                     let args_vec = rm.get_args().to_vec();
                     let args = darpi::Args{
                         request: r,
@@ -308,27 +256,175 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                         route_args: #id::get_tuple_args(&route_str, &args_vec),
                     };
                     let mut rb = Handler::call(#ha, args).await.unwrap();
+                    #(#scope_res )*
                     #(#middleware_res )*
                     #(#jobs_res )*
+
+                    if !rb.headers().contains_key(darpi::header::CONTENT_LENGTH) {
+                        use darpi::HttpBody;
+                        if let Some(len) = rb.body().size_hint().exact() {
+                            rb.headers_mut().insert(darpi::header::CONTENT_LENGTH, len.into());
+                        }
+                    }
+                    *rb.body_mut() = darpi::Body::empty();
                     return Ok::<_, std::convert::Infallible>(rb);
                 }
+            });
+            allowed.push(head);
+        }
+
+        let options = http::Method::OPTIONS.to_string();
+        if !allowed.contains(&options) {
+            allowed.push(options);
+        }
+        let allow = LitStr::new(&allowed.join(", "), Span::call_site());
+
+        route_match.push(quote! {
+            #gi => {
+                #(#method_matches )*
+
+                let status = if method == darpi::Method::OPTIONS {
+                    darpi::StatusCode::NO_CONTENT
+                } else {
+                    darpi::StatusCode::METHOD_NOT_ALLOWED
+                };
+
+                // the app response middleware still sees the generated response
+                let mut rb = darpi::Response::builder()
+                    .status(status)
+                    .header(darpi::header::ALLOW, #allow)
+                    .body(darpi::Body::empty())
+                    .unwrap();
+                #(#middleware_res )*
+                #(#jobs_res )*
+                return Ok::<_, std::convert::Infallible>(rb);
             }
         });
-
-        let r = make_route_lit(
-            &id,
-            &h.method.path.segments.last().unwrap().ident.to_string(),
-            h.route.to_token_stream(),
-        )?;
-        route_defs.push(r);
     }
 
+    let normalize = match routing.trailing_slash {
+        TrailingSlash::Strict => quote! {},
+        TrailingSlash::Redirect => quote! {
+            if route_m.is_none() {
+                if let Some((path, _)) = inner_router.route_normalized(&route_str) {
+                    let location = match r.uri().query() {
+                        Some(query) => format!("{}?{}", path, query),
+                        None => path,
+                    };
+                    return Ok::<_, std::convert::Infallible>(darpi::Response::builder()
+                        .status(darpi::StatusCode::PERMANENT_REDIRECT)
+                        .header(darpi::header::LOCATION, location)
+                        .body(darpi::Body::empty())
+                        .unwrap());
+                }
+            }
+        },
+        TrailingSlash::Match => quote! {
+            let (route_str, route_m) = match route_m {
+                Some(rm) => (route_str, Some(rm)),
+                None => match inner_router.route_normalized(&route_str) {
+                    Some((path, rm)) => (path, Some(rm)),
+                    None => (route_str, None),
+                },
+            };
+        },
+    };
+
+    // the document is built once, when the app is created
+    let (openapi_doc, openapi) = match config.openapi {
+        Some(openapi) => {
+            let route = &openapi.route;
+            let title = &openapi.title;
+            let version = &openapi.version;
+            let is_yaml = route.value().ends_with(".yaml") || route.value().ends_with(".yml");
+
+            let operations: Vec<proc_macro2::TokenStream> = handlers
+                .iter()
+                .map(|h| {
+                    let route = &h.route;
+                    let method = h.method.path.segments.last().unwrap().ident.to_string();
+                    let ha = &h.handler;
+                    let operation_id = match &h.name {
+                        Some(name) => name.value(),
+                        None => ha.to_token_stream().to_string().replace(' ', ""),
+                    };
+                    quote! {
+                        .operation(#route, #method, #operation_id, darpi::openapi::describe(&#ha))
+                    }
+                })
+                .collect();
+
+            let (content_type, body) = if is_yaml {
+                (quote! {"application/yaml"}, quote! {doc.to_yaml()})
+            } else {
+                (quote! {"application/json"}, quote! {doc.to_json()})
+            };
+
+            (
+                quote! {{
+                    let doc = darpi::openapi::Document::new(#title, #version)
+                        #(#operations)*;
+                    darpi::body::Bytes::from(#body)
+                }},
+                quote! {
+                    if (method == darpi::Method::GET || method == darpi::Method::HEAD) && route_str == #route {
+                        let len = inner_openapi.len();
+                        let body = if method == darpi::Method::HEAD {
+                            darpi::Body::empty()
+                        } else {
+                            darpi::Body::from(inner_openapi)
+                        };
+                        return Ok::<_, std::convert::Infallible>(darpi::Response::builder()
+                            .header(darpi::header::CONTENT_TYPE, #content_type)
+                            .header(darpi::header::CONTENT_LENGTH, len)
+                            .body(body)
+                            .unwrap());
+                    }
+                },
+            )
+        }
+        None => (quote! {darpi::body::Bytes::new()}, quote! {}),
+    };
+
+    let error_renderer = match config.error_renderer {
+        Some(renderer) => quote! {#renderer},
+        None => quote! {darpi::problem::PlainText},
+    };
+
+    let not_found = config.fallback.map_or(
+        quote! {
+            return  async {
+                 Ok::<_, std::convert::Infallible>(darpi::Response::builder()
+                    .status(darpi::StatusCode::NOT_FOUND)
+                    .body(darpi::Body::empty())
+                    .unwrap())
+            }.await;
+        },
+        |fallback| {
+            quote! {
+                let args = darpi::Args{
+                    request: r,
+                    container: inner_module.clone(),
+                    route_args: (),
+                };
+                let mut rb = Handler::call(#fallback, args).await.unwrap();
+                #(#middleware_res )*
+                #(#jobs_res )*
+                return Ok::<_, std::convert::Infallible>(rb);
+            }
+        },
+    );
+
     let app = quote! {
         #(#route_defs )*
+        #(#route_names )*
 
          pub struct AppImpl {
             #module_def
+            #tls_def
             router: std::sync::Arc<darpi::gonzales::Router>,
+            error_renderer: std::sync::Arc<dyn darpi::problem::ErrorRenderer>,
+            openapi: darpi::body::Bytes,
             address: std::net::SocketAddr,
             rx: tokio::sync::oneshot::Receiver<()>,
             tx: Option<tokio::sync::oneshot::Sender<()>>,
@@ -347,9 +443,13 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                 let router = std::sync::Arc::new(darpi::gonzales::RouterBuilder::new().build(routes_vec));
 
                 #module_let
+                #tls_let
                 Self {
                     #module_self
+                    #tls_self
                     router: router,
+                    error_renderer: std::sync::Arc::new(#error_renderer),
+                    openapi: #openapi_doc,
                     address: address,
                     rx: rx,
                     tx: Some(tx),
@@ -361,6 +461,10 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
 
         #[darpi::async_trait]
         impl darpi::App for AppImpl {
+            fn routes(&self) -> Vec<darpi::RouteInfo> {
+                vec![#(#route_infos ,)*]
+            }
+
             fn startup_notify(&mut self) -> Option<tokio::sync::oneshot::Receiver<()>> {
                 if let Some(_) = self.start_tx {
                     return None;
@@ -378,6 +482,8 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                 let address = self.address;
                 let module = self.module.clone();
                 let router = self.router.clone();
+                let error_renderer = self.error_renderer.clone();
+                let openapi = self.openapi.clone();
                 let start_tx = self.start_tx;
                 let rx = self.rx;
 
@@ -390,6 +496,8 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                 let make_svc = darpi::service::make_service_fn(move |_conn| {
                     let inner_module = std::sync::Arc::clone(&module);
                     let inner_router = std::sync::Arc::clone(&router);
+                    let inner_renderer = std::sync::Arc::clone(&error_renderer);
+                    let inner_openapi = openapi.clone();
 
                     async move {
                         Ok::<_, std::convert::Infallible>(darpi::service::service_fn(move |mut r: darpi::Request<darpi::Body>| {
@@ -404,15 +512,21 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                             use darpi::Route;
                             let inner_module = std::sync::Arc::clone(&inner_module);
                             let inner_router = std::sync::Arc::clone(&inner_router);
+                            let inner_renderer = std::sync::Arc::clone(&inner_renderer);
+                            #[allow(unused_variables)]
+                            let inner_openapi = inner_openapi.clone();
 
-                            async move {
+                            darpi::problem::with_error_renderer(inner_renderer, async move {
                                 let route_str = r.uri().path().to_string();
                                 let method = r.method().clone();
 
                                 #(#middleware_req )*
                                 #(#jobs_req )*
 
+                                #openapi
+
                                 let route_m = inner_router.route(&route_str);
+                                #normalize
 
                                 if let Some(rm) = route_m {
                                     match rm.get_index() {
@@ -421,18 +535,13 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
                                     }
                                 }
 
-                                return  async {
-                                     Ok::<_, std::convert::Infallible>(darpi::Response::builder()
-                                        .status(darpi::StatusCode::NOT_FOUND)
-                                        .body(darpi::Body::empty())
-                                        .unwrap())
-                                }.await;
-                            }
+                                #not_found
+                            })
                         }))
                     }
                 });
 
-                let server = darpi::Server::bind(&address).serve(make_svc);
+                #server
                 let graceful = server.with_graceful_shutdown(async { rx.await.ok(); });
                 if let Some(start) = start_tx {
                     let _ = start.send(());
@@ -453,103 +562,723 @@ pub(crate) fn make_app(config: Config) -> Result<TokenStream, SynError> {
     Ok(tokens.into())
 }
 
-fn make_route_lit(
-    struct_ident: &Ident,
-    method_type: &str,
-    r: proc_macro2::TokenStream,
-) -> Result<proc_macro2::TokenStream, syn::Error> {
-    let r_str = r.to_token_stream().to_string();
-    let mut parts = vec![];
-    let mut args = vec![];
-    let mut args_i = 0;
+fn make_middleware(
+    middleware: Option<ReqResArray>,
+    prefix: &str,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    middleware.map_or(Default::default(), |middleware| {
+        let mut middleware_req = vec![];
+        let mut middleware_res = vec![];
+        let mut i = 0u16;
+
+        middleware.request.map(|rm| {
+           rm.iter().for_each(|e| {
+                let m_arg_ident = format_ident!("{}m_arg_{}", prefix, i);
+
+                let (name, m_args) = match e {
+                    Func::Call(expr_call) => {
+                        let m_args: Vec<proc_macro2::TokenStream> = expr_call.args.iter().map(|arg| {
+                            if let SynExpr::Call(expr_call) = arg {
+                                if expr_call.func.to_token_stream().to_string() == "request" {
+                                    let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
+                                    let i_ident = format_ident!("{}m_arg_{}", prefix, index);
+                                    return quote!{#i_ident.clone()};
+                                }
+                            }
+                            quote! {#arg}
+                        }).collect();
+
+                        let q = if m_args.len() > 1 {
+                            quote! {(#(#m_args ,)*)}
+                        } else if m_args.len() == 1 {
+                            quote! {#(#m_args ,)*}
+                        } else {
+                            quote! {()}
+                        };
+
+                        (expr_call.func.to_token_stream(), q)
+                    },
+                    Func::Path(expr_path) => {
+                        (expr_path.to_token_stream(), quote! {()})
+                    }
+                };
+
+
+                middleware_req.push(quote! {
+                let #m_arg_ident = match #name::call(&mut r, inner_module.clone(), #m_args).await {
+                    Ok(k) => k,
+                    Err(e) => return Ok(e.respond_err()),
+                };
+            });
+                i += 1;
+            });
+        });
+
+        let mut i = 0u16;
+
+        middleware.response.map(|ref mut rm| {
+            rm.iter_mut().for_each(|e| {
+                let r_m_arg_ident = format_ident!("{}res_m_arg_{}", prefix, i);
+                let (name, m_args) = match e {
+                    Func::Call(expr_call) => {
+                        let m_args: Vec<proc_macro2::TokenStream> = expr_call.args.iter_mut().map(|arg| {
+                            if let SynExpr::Call(expr_call) = arg {
+                                if expr_call.func.to_token_stream().to_string() == "request" {
+                                    let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
+                                    let i_ident = format_ident!("{}m_arg_{}", prefix, index);
+                                    return quote!{#i_ident.clone()};
+                                }
+                                if expr_call.func.to_token_stream().to_string() == "response" {
+                                    let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
+                                    let r_m_arg_ident = format_ident!("{}res_m_arg_{}", prefix, index);
+                                    return quote!{#r_m_arg_ident.clone()};
+                                }
+                            }
+                            if  let SynExpr::Tuple(tuple) = arg.clone() {
+                                let tuple_expr: Vec<proc_macro2::TokenStream> = tuple.elems.iter().map(|tuple_arg| {
+                                    if let SynExpr::Call(expr_call) = tuple_arg {
+                                        if expr_call.func.to_token_stream().to_string() == "request" {
+                                            let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
+                                            let i_ident = format_ident!("{}m_arg_{}", prefix, index);
+                                            return quote!{#i_ident.clone()};
+                                        }
+                                        if expr_call.func.to_token_stream().to_string() == "response" {
+                                            let index: u16 = expr_call.args.first().unwrap().to_token_stream().to_string().parse().unwrap();
+                                            let r_m_arg_ident = format_ident!("{}res_m_arg_{}", prefix, index);
+                                            return quote!{#r_m_arg_ident.clone()};
+                                        }
+                                    }
+                                    quote! {#tuple_arg}
+                                }).collect();
+                                return quote! {( #(#tuple_expr ,)* )};
+                            }
+                            quote! {#arg}
+                        }).collect();
+
+                        let q = if m_args.len() > 1 {
+                            quote! {(#(#m_args ,)*)}
+                        } else if m_args.len() == 1 {
+                            quote! {#(#m_args ,)*}
+                        } else {
+                            quote! {()}
+                        };
+
+                        (expr_call.func.to_token_stream(), q)
+
+                    },
+                    Func::Path(expr_path) => {
+                        (expr_path.to_token_stream(), quote! {()})
+                    }
+                };
+
+                middleware_res.push(quote! {
+                let #r_m_arg_ident = match #name::call(&mut rb, inner_module.clone(), #m_args).await {
+                    Ok(k) => k,
+                    Err(e) => return Ok(e.respond_err()),
+                };
+            });
+                i += 1;
+            });
+        });
+
+        (
+            middleware_req,
+            middleware_res,
+        )
+    })
+}
+
+fn make_jobs(
+    jobs: Option<ReqResArray>,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    jobs.map_or(Default::default(), |jobs| {
+        let mut jobs_req = vec![];
+        let mut jobs_res = vec![];
+
+        jobs.request.map(|jr| {
+            jr.iter().for_each(|e| {
+                let (name, m_args) = match e {
+                    Func::Call(ec) => {
+                        let name = ec.func.to_token_stream();
+                        let m_args: Vec<proc_macro2::TokenStream> = ec
+                            .args
+                            .iter()
+                            .map(|arg| {
+                                quote! {#arg}
+                            })
+                            .collect();
+
+                        let q = if m_args.len() > 1 {
+                            quote! {(#(#m_args ,)*)}
+                        } else if m_args.len() == 1 {
+                            quote! {#(#m_args ,)*}
+                        } else {
+                            quote! {()}
+                        };
+
+                        (name, q)
+                    }
+                    Func::Path(path) => (path.to_token_stream(), quote! {()}),
+                };
+
+                jobs_req.push(quote! {
+                    match #name::call(&r, inner_module.clone(), #m_args).await.into() {
+                        darpi::job::Job::CpuBound(function) => {
+                                let res = darpi::spawn(function);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue CpuBound job err: {}", e);
+                                }
+                            }
+                            darpi::job::Job::IOBlocking(function) => {
+                                let res = darpi::spawn(function);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue IOBlocking job err: {}", e);
+                                }
+                            }
+                            darpi::job::Job::Future(fut) => {
+                                let res = darpi::spawn(fut);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue Future job err: {}", e);
+                                }
+                            }
+                    };
+                });
+            });
+        });
+
+        jobs.response.map(|ref mut jr| {
+            jr.iter_mut().for_each(|e| {
+                let (name, m_args) = match e {
+                    Func::Call(ec) => {
+                        let name = ec.func.to_token_stream();
+                        let m_args: Vec<proc_macro2::TokenStream> = ec
+                            .args
+                            .iter()
+                            .map(|arg| {
+                                quote! {#arg}
+                            })
+                            .collect();
+
+                        let q = if m_args.len() > 1 {
+                            quote! {(#(#m_args ,)*)}
+                        } else if m_args.len() == 1 {
+                            quote! {#(#m_args ,)*}
+                        } else {
+                            quote! {()}
+                        };
+                        (name, q)
+                    }
+                    Func::Path(p) => (p.to_token_stream(), quote! {()}),
+                };
+
+                jobs_res.push(quote! {
+                    match #name::call(&rb, inner_module.clone(), #m_args).await.into() {
+                        darpi::job::Job::CpuBound(function) => {
+                                let res = darpi::spawn(function);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue CpuBound job err: {}", e);
+                                }
+                            }
+                            darpi::job::Job::IOBlocking(function) => {
+                                let res = darpi::spawn(function);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue IOBlocking job err: {}", e);
+                                }
+                            }
+                            darpi::job::Job::Future(fut) => {
+                                let res = darpi::spawn(fut);
+                                if let Err(e) = res {
+                                    log::warn!("could not queue Future job err: {}", e);
+                                }
+                            }
+                    };
+                });
+            });
+        });
+
+        (jobs_req, jobs_res)
+    })
+}
+
+fn middleware_names(middleware: &Option<ReqResArray>) -> (Vec<String>, Vec<String>) {
+    let names = |funcs: &Option<Punctuated<Func, token::Comma>>| -> Vec<String> {
+        funcs
+            .iter()
+            .flatten()
+            .map(|f| {
+                let name = match f {
+                    Func::Call(expr_call) => expr_call.func.to_token_stream(),
+                    Func::Path(expr_path) => expr_path.to_token_stream(),
+                };
+                name.to_string().replace(' ', "")
+            })
+            .collect()
+    };
+
+    middleware.as_ref().map_or(Default::default(), |m| {
+        (names(&m.request), names(&m.response))
+    })
+}
+
+fn make_route_info(
+    h: &Handler,
+    app_req: &[String],
+    app_res: &[String],
+    scopes: &[(Vec<String>, Vec<String>)],
+) -> proc_macro2::TokenStream {
+    let route = &h.route;
+    let method = h.method.path.segments.last().unwrap().ident.to_string();
+    let handler = h.handler.to_token_stream().to_string().replace(' ', "");
+    let name = match &h.name {
+        Some(name) => quote! {Some(#name)},
+        None => quote! {None},
+    };
+
+    let route_str = route.to_token_stream().to_string();
+    let args: Vec<&str> = route_str
+        .trim_matches('"')
+        .split('/')
+        .filter(|part| part.starts_with('{') && part.ends_with('}'))
+        .map(|part| {
+            part[1..part.len() - 1]
+                .trim_start_matches('*')
+                .split(':')
+                .next()
+                .unwrap()
+        })
+        .collect();
+
+    // the same order the generated dispatch runs them in
+    let mut req: Vec<&String> = app_req.iter().collect();
+    let mut res = vec![];
+    for si in h.scopes.iter() {
+        req.extend(scopes[*si].0.iter());
+    }
+    for si in h.scopes.iter().rev() {
+        res.extend(scopes[*si].1.iter());
+    }
+    res.extend(app_res.iter());
+
+    quote! {
+        darpi::RouteInfo {
+            route: #route,
+            method: #method,
+            handler: #handler,
+            name: #name,
+            args: vec![#(#args ,)*],
+            request_middleware: vec![#(#req ,)*],
+            response_middleware: vec![#(#res ,)*],
+        }
+    }
+}
+
+// strips the argument names and keeps their constraints
+// `/users/{id:u64}` and `/users/{user_id:u64}` are the same route
+fn normalize_route(route: &str) -> String {
+    let mut normalized = String::with_capacity(route.len());
+    let mut in_name = false;
+
+    for c in route.trim_matches('"').chars() {
+        match c {
+            '{' => {
+                in_name = true;
+                normalized.push(c);
+            }
+            ':' | '}' if in_name => {
+                in_name = false;
+                normalized.push(c);
+            }
+            // `{*path}` and `{path}` are different routes
+            '*' if in_name && normalized.ends_with('{') => normalized.push(c),
+            _ if in_name => {}
+            _ => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+fn route_arg_names(route: &str) -> Vec<String> {
+    route
+        .trim_matches('"')
+        .split('/')
+        .filter(|part| part.starts_with('{') && part.ends_with('}'))
+        .map(|part| {
+            let arg = part[1..part.len() - 1].trim_start_matches('*');
+            arg.split(':').next().unwrap_or(arg).to_string()
+        })
+        .collect()
+}
+
+fn make_route_lit(
+    struct_ident: &Ident,
+    method_type: &str,
+    r: proc_macro2::TokenStream,
+    percent_decode: bool,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let r_str = r.to_token_stream().to_string();
+    let mut parts = vec![];
+    let mut args = vec![];
+    let mut args_i = 0;
+
+    let segments_len = r_str.split('/').count();
+    for (i, part) in r_str.split('/').enumerate() {
+        let part = part.trim_end_matches('"');
+        if part == "*" && i != segments_len - 1 {
+            return Err(Error::new_spanned(
+                r,
+                "`*` matches the rest of the path and is only allowed at the end of a route",
+            ));
+        }
+
+        let starts = part.starts_with('{');
+        let ends = part.ends_with('}');
+        if starts ^ ends {
+            return Err(Error::new_spanned(
+                r,
+                "route arguments must start with `{` and end with `}`",
+            ));
+        }
+
+        if starts && ends {
+            let mut arg = &part[1..part.len() - 1];
+            if let Some(rest) = arg.strip_prefix('*') {
+                if i != segments_len - 1 {
+                    return Err(Error::new_spanned(
+                        r,
+                        "`{*name}` matches the rest of the path and is only allowed at the end of a route",
+                    ));
+                }
+                if rest.contains(':') {
+                    return Err(Error::new_spanned(
+                        r,
+                        "`{*name}` matches the rest of the path and can not have a constraint",
+                    ));
+                }
+                arg = rest;
+            }
+            let (name, constraint) = match arg.find(':') {
+                Some(i) => (&arg[..i], Some(&arg[i + 1..])),
+                None => (arg, None),
+            };
+
+            if name.is_empty() {
+                return Err(Error::new_spanned(r, "route arguments must have a name"));
+            }
+
+            if let Some(constraint) = constraint {
+                if let Err(e) = gonzales::Constraint::parse(constraint) {
+                    return Err(Error::new_spanned(
+                        r,
+                        format!("invalid constraint for route argument `{}`: {}", name, e),
+                    ));
+                }
+            }
+
+            args.push((args_i, name.to_string()));
+            args_i += 1;
+        } else {
+            parts.push((i, part.to_string()));
+        }
+    }
+
+    let method_str_lit = LitStr::new(method_type, Span::call_site());
+    let mut prop_values = HashMap::new();
+
+    for (i, prop) in args.iter() {
+        let prop_name = format_ident!("arg{}", i);
+        prop_values.insert((prop_name.clone(), i), prop);
+    }
+
+    let mut tuple_type = vec![];
+    let mut get_args_lines = vec![];
+    for ((_, index), sorter) in prop_values {
+        let i = syn::Index::from(*index);
+        let arg = if percent_decode {
+            quote! {darpi::request::percent_decode(&route_str[r[#i].0..r[#i].1])}
+        } else {
+            quote! {route_str[r[#i].0..r[#i].1].to_string()}
+        };
+        get_args_lines.push((arg, sorter));
+        tuple_type.push(quote! {String});
+    }
+
+    get_args_lines.sort_by(|a, b| a.1.cmp(b.1));
+
+    let sorted_get_args_lines: Vec<proc_macro2::TokenStream> =
+        get_args_lines.iter().map(|a| a.0.clone()).collect();
+
+    Ok(quote! {
+        struct #struct_ident;
+
+        impl darpi::Route<(#(#tuple_type ,)*)> for #struct_ident {
+            #[inline(always)]
+            fn is_match(method: &str) -> bool {
+                #method_str_lit == method
+            }
+
+            #[inline(always)]
+            fn get_tuple_args(route_str: &str, r: &Vec<(usize, usize)>) -> (#(#tuple_type ,)*) {
+                (#(#sorted_get_args_lines ,)*)
+            }
+        }
+    })
+}
+
+#[derive(Debug)]
+pub(crate) enum Address {
+    Lit(LitStr),
+    Ident(Ident),
+}
+
+impl Parse for Address {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        if input.peek(LitStr) {
+            let lit_str: LitStr = input.parse()?;
+            return Ok(Address::Lit(lit_str));
+        }
+        let ident: Ident = input.parse()?;
+        Ok(Address::Ident(ident))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Container {
+    pub factory: ExprCall,
+    pub ttype: syn::Path,
+}
+
+impl Parse for Container {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let name: Ident = input.parse()?;
+        let _: token::Colon = input.parse()?;
+
+        let mut factory: Option<ExprCall> = None;
+        let mut ttype: Option<syn::Path> = None;
+
+        let content;
+        let _ = braced!(content in input);
+
+        while !content.is_empty() {
+            if content.peek(token::Comma) {
+                let _: token::Comma = content.parse()?;
+            }
+
+            let key: Ident = if content.peek(token::Type) {
+                let _: token::Type = content.parse()?;
+                format_ident!("type")
+            } else {
+                content.parse()?
+            };
+            let _: token::Colon = content.parse()?;
+
+            if key == "factory" {
+                let f: ExprCall = content.parse()?;
+                factory = Some(f);
+                continue;
+            }
+            if key == "type" {
+                let t: syn::Path = content.parse()?;
+                ttype = Some(t);
+                continue;
+            }
+
+            return Err(Error::new_spanned(
+                key.clone(),
+                format!(
+                    "unknown key: `{}`. Only `factory` and `type` are allowed",
+                    key
+                ),
+            ));
+        }
+
+        let factory = match factory {
+            Some(r) => r,
+            None => return Err(SynError::new_spanned(name, "missing `factory`")),
+        };
+
+        let ttype = match ttype {
+            Some(r) => r,
+            None => return Err(Error::new_spanned(name, "missing `type`")),
+        };
+
+        Ok(Container { factory, ttype })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum TrailingSlash {
+    Strict,
+    Redirect,
+    Match,
+}
+
+/// serves the OpenAPI document of the app
+/// the document is yaml if the route ends with `.yaml` or `.yml`, json otherwise
+#[derive(Debug)]
+pub(crate) struct OpenApi {
+    pub route: LitStr,
+    pub title: LitStr,
+    pub version: LitStr,
+}
+
+impl Parse for OpenApi {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let _: Ident = input.parse()?;
+        let _: token::Colon = input.parse()?;
+
+        let content;
+        let brace = braced!(content in input);
+
+        let mut route = None;
+        let mut title = None;
+        let mut version = None;
+
+        while !content.is_empty() {
+            if content.peek(token::Comma) {
+                let _: token::Comma = content.parse()?;
+            }
+            if content.is_empty() {
+                break;
+            }
+
+            let key: Ident = content.parse()?;
+            let _: token::Colon = content.parse()?;
+            let value: LitStr = content.parse()?;
+
+            if key == "route" {
+                route = Some(value);
+                continue;
+            }
+            if key == "title" {
+                title = Some(value);
+                continue;
+            }
+            if key == "version" {
+                version = Some(value);
+                continue;
+            }
 
-    for (i, part) in r_str.split('/').enumerate() {
-        let part = part.trim_end_matches('"');
-        let starts = part.starts_with('{');
-        let ends = part.ends_with('}');
-        if starts ^ ends {
             return Err(Error::new_spanned(
-                r,
-                "route arguments must start with `{` and end with `}`",
+                key.clone(),
+                format!(
+                    "unknown key: `{}`. Only `route`, `title` and `version` are allowed",
+                    key
+                ),
             ));
         }
 
-        if starts && ends {
-            args.push((args_i, part.to_string()));
-            args_i += 1;
-        } else {
-            parts.push((i, part.to_string()));
-        }
-    }
-
-    let method_str_lit = LitStr::new(method_type, Span::call_site());
-    let mut prop_values = HashMap::new();
+        let route = match route {
+            Some(r) => r,
+            None => return Err(SynError::new(brace.span, "missing `route`")),
+        };
 
-    for (i, prop) in args.iter() {
-        let prop_name = format_ident!("arg{}", i);
-        prop_values.insert((prop_name.clone(), i), prop);
+        Ok(OpenApi {
+            route,
+            title: title.unwrap_or_else(|| LitStr::new("darpi", Span::call_site())),
+            version: version.unwrap_or_else(|| LitStr::new("0.1.0", Span::call_site())),
+        })
     }
+}
 
-    let mut tuple_type = vec![];
-    let mut get_args_lines = vec![];
-    for ((_, index), sorter) in prop_values {
-        let i = syn::Index::from(*index);
-        get_args_lines.push((quote! {route_str[r[#i].0..r[#i].1].to_string()}, sorter));
-        tuple_type.push(quote! {String});
+#[derive(Debug)]
+pub(crate) struct Routing {
+    pub trailing_slash: TrailingSlash,
+    pub percent_decode: bool,
+}
+
+impl Default for Routing {
+    fn default() -> Self {
+        Self {
+            trailing_slash: TrailingSlash::Strict,
+            percent_decode: false,
+        }
     }
+}
 
-    get_args_lines.sort_by(|a, b| a.1.cmp(b.1));
+impl Parse for Routing {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let _: Ident = input.parse()?;
+        let _: token::Colon = input.parse()?;
 
-    let sorted_get_args_lines: Vec<proc_macro2::TokenStream> =
-        get_args_lines.iter().map(|a| a.0.clone()).collect();
+        let mut routing = Routing::default();
 
-    Ok(quote! {
-        struct #struct_ident;
+        let content;
+        let _ = braced!(content in input);
 
-        impl darpi::Route<(#(#tuple_type ,)*)> for #struct_ident {
-            #[inline(always)]
-            fn is_match(method: &str) -> bool {
-                #method_str_lit == method
+        while !content.is_empty() {
+            if content.peek(token::Comma) {
+                let _: token::Comma = content.parse()?;
             }
-
-            #[inline(always)]
-            fn get_tuple_args(route_str: &str, r: &Vec<(usize, usize)>) -> (#(#tuple_type ,)*) {
-                (#(#sorted_get_args_lines ,)*)
+            if content.is_empty() {
+                break;
             }
-        }
-    })
-}
 
-#[derive(Debug)]
-pub(crate) enum Address {
-    Lit(LitStr),
-    Ident(Ident),
-}
+            let key: Ident = content.parse()?;
+            let _: token::Colon = content.parse()?;
 
-impl Parse for Address {
-    fn parse(input: ParseStream) -> SynResult<Self> {
-        if input.peek(LitStr) {
-            let lit_str: LitStr = input.parse()?;
-            return Ok(Address::Lit(lit_str));
+            if key == "trailing_slash" {
+                // `match` is a keyword
+                let policy = if content.peek(token::Match) {
+                    let _: token::Match = content.parse()?;
+                    TrailingSlash::Match
+                } else {
+                    let policy: Ident = content.parse()?;
+                    if policy == "strict" {
+                        TrailingSlash::Strict
+                    } else if policy == "redirect" {
+                        TrailingSlash::Redirect
+                    } else {
+                        return Err(Error::new_spanned(
+                            policy,
+                            "invalid `trailing_slash`. Only `strict`, `redirect` and `match` are allowed",
+                        ));
+                    }
+                };
+                routing.trailing_slash = policy;
+                continue;
+            }
+            if key == "percent_decode" {
+                let b: syn::LitBool = content.parse()?;
+                routing.percent_decode = b.value;
+                continue;
+            }
+
+            return Err(Error::new_spanned(
+                key.clone(),
+                format!(
+                    "unknown key: `{}`. Only `trailing_slash` and `percent_decode` are allowed",
+                    key
+                ),
+            ));
         }
-        let ident: Ident = input.parse()?;
-        Ok(Address::Ident(ident))
+
+        Ok(routing)
     }
 }
 
 #[derive(Debug)]
-pub(crate) struct Container {
-    pub factory: ExprCall,
-    pub ttype: syn::Path,
+pub(crate) enum Tls {
+    Files {
+        cert: Expr,
+        key: Expr,
+        client_ca: Option<Expr>,
+        client_auth: Option<Ident>,
+        reload: Option<Expr>,
+    },
+    Config(Expr),
 }
 
-impl Parse for Container {
+impl Parse for Tls {
     fn parse(input: ParseStream) -> SynResult<Self> {
         let name: Ident = input.parse()?;
         let _: token::Colon = input.parse()?;
 
-        let mut factory: Option<ExprCall> = None;
-        let mut ttype: Option<syn::Path> = None;
+        let mut cert: Option<Expr> = None;
+        let mut key: Option<Expr> = None;
+        let mut client_ca: Option<Expr> = None;
+        let mut client_auth: Option<Ident> = None;
+        let mut reload: Option<Expr> = None;
+        let mut config: Option<Expr> = None;
 
         let content;
         let _ = braced!(content in input);
@@ -559,45 +1288,87 @@ impl Parse for Container {
                 let _: token::Comma = content.parse()?;
             }
 
-            let key: Ident = if content.peek(token::Type) {
-                let _: token::Type = content.parse()?;
-                format_ident!("type")
-            } else {
-                content.parse()?
-            };
+            if content.is_empty() {
+                break;
+            }
+            let key_ident: Ident = content.parse()?;
             let _: token::Colon = content.parse()?;
 
-            if key == "factory" {
-                let f: ExprCall = content.parse()?;
-                factory = Some(f);
+            if key_ident == "cert" {
+                cert = Some(content.parse()?);
                 continue;
             }
-            if key == "type" {
-                let t: syn::Path = content.parse()?;
-                ttype = Some(t);
+            if key_ident == "key" {
+                key = Some(content.parse()?);
+                continue;
+            }
+            if key_ident == "client_ca" {
+                client_ca = Some(content.parse()?);
+                continue;
+            }
+            if key_ident == "client_auth" {
+                let ca: Ident = content.parse()?;
+                if ca != "optional" && ca != "required" {
+                    return Err(Error::new_spanned(
+                        ca,
+                        "invalid `client_auth`. Only `optional` and `required` are allowed",
+                    ));
+                }
+                client_auth = Some(ca);
+                continue;
+            }
+            if key_ident == "reload" {
+                reload = Some(content.parse()?);
+                continue;
+            }
+            if key_ident == "config" {
+                config = Some(content.parse()?);
                 continue;
             }
 
             return Err(Error::new_spanned(
-                key.clone(),
+                key_ident.clone(),
                 format!(
-                    "unknown key: `{}`. Only `factory` and `type` are allowed",
-                    key
+                    "unknown key: `{}`. Only `cert`, `key`, `client_ca`, `client_auth`, `reload` and `config` are allowed",
+                    key_ident
                 ),
             ));
         }
 
-        let factory = match factory {
+        if let Some(config) = config {
+            if cert.is_some() || key.is_some() || client_ca.is_some() || reload.is_some() {
+                return Err(Error::new_spanned(
+                    name,
+                    "`config` cannot be combined with `cert`, `key`, `client_ca` or `reload`",
+                ));
+            }
+            return Ok(Tls::Config(config));
+        }
+
+        let cert = match cert {
             Some(r) => r,
-            None => return Err(SynError::new_spanned(name, "missing `factory`")),
+            None => return Err(Error::new_spanned(name, "missing `cert`")),
         };
 
-        let ttype = match ttype {
+        let key = match key {
             Some(r) => r,
-            None => return Err(Error::new_spanned(name, "missing `type`")),
+            None => return Err(Error::new_spanned(name, "missing `key`")),
         };
 
-        Ok(Container { factory, ttype })
+        if client_auth.is_some() && client_ca.is_none() {
+            return Err(Error::new_spanned(
+                name,
+                "`client_auth` requires `client_ca`",
+            ));
+        }
+
+        Ok(Tls::Files {
+            cert,
+            key,
+            client_ca,
+            client_auth,
+            reload,
+        })
     }
 }
 
@@ -702,9 +1473,15 @@ impl Parse for ReqResArray {
 pub struct Config {
     pub(crate) address: Address,
     pub(crate) container: Option<Container>,
+    pub(crate) tls: Option<Tls>,
     pub(crate) jobs: Option<ReqResArray>,
     pub(crate) middleware: Option<ReqResArray>,
-    pub(crate) handlers: Punctuated<Handler, token::Comma>,
+    pub(crate) handlers: Vec<Handler>,
+    pub(crate) scopes: Vec<Scope>,
+    pub(crate) fallback: Option<ExprPath>,
+    pub(crate) error_renderer: Option<Expr>,
+    pub(crate) routing: Option<Routing>,
+    pub(crate) openapi: Option<OpenApi>,
 }
 
 impl Parse for Config {
@@ -714,9 +1491,15 @@ impl Parse for Config {
 
         let mut address: Option<Address> = None;
         let mut container: Option<Container> = None;
+        let mut tls: Option<Tls> = None;
         let mut jobs: Option<ReqResArray> = None;
         let mut middleware: Option<ReqResArray> = None;
-        let mut handlers: Option<Punctuated<Handler, token::Comma>> = None;
+        let mut handlers: Option<Vec<Handler>> = None;
+        let mut scopes: Vec<Scope> = vec![];
+        let mut fallback: Option<ExprPath> = None;
+        let mut error_renderer: Option<Expr> = None;
+        let mut routing: Option<Routing> = None;
+        let mut openapi: Option<OpenApi> = None;
 
         while !content.is_empty() {
             if content.peek(token::Comma) {
@@ -737,6 +1520,11 @@ impl Parse for Config {
                 container = Some(c);
                 continue;
             }
+            if key == "tls" {
+                let t: Tls = content.parse()?;
+                tls = Some(t);
+                continue;
+            }
             if key == "jobs" {
                 let j: ReqResArray = content.parse()?;
                 jobs = Some(j);
@@ -747,30 +1535,88 @@ impl Parse for Config {
                 middleware = Some(m);
                 continue;
             }
+            if key == "openapi" {
+                let o: OpenApi = content.parse()?;
+                openapi = Some(o);
+                continue;
+            }
+            if key == "routing" {
+                let r: Routing = content.parse()?;
+                routing = Some(r);
+                continue;
+            }
+            if key == "fallback" {
+                let _: Ident = content.parse()?;
+                let _: token::Colon = content.parse()?;
+                let f: ExprPath = content.parse()?;
+                fallback = Some(f);
+                continue;
+            }
+            if key == "error_renderer" {
+                let _: Ident = content.parse()?;
+                let _: token::Colon = content.parse()?;
+                let e: Expr = content.parse()?;
+                error_renderer = Some(e);
+                continue;
+            }
 
             if key == "handlers" {
                 let _: Ident = content.parse()?;
                 let _: token::Colon = content.parse()?;
                 let br;
                 let _ = bracketed!(br in content);
-                let h: Punctuated<Handler, token::Comma> = Punctuated::parse(&br)?;
+                let entries: Punctuated<Entry, token::Comma> = Punctuated::parse(&br)?;
+
+                let mut h = vec![];
+                flatten(entries, "", &[], &mut scopes, &mut h);
 
                 let mut handler_validation = HashMap::new();
+                let mut names = HashMap::new();
 
+                // the argument names do not take part in the routing
+                // `/users/{id}` and `/users/{name}` would shadow each other
                 for h in h.iter() {
-                    let key = format!(
-                        "{}{}",
-                        h.route.lit.to_token_stream(),
-                        h.method.path.to_token_stream()
-                    );
+                    let route = h.route.lit.to_token_stream().to_string();
+                    let method = h.method.path.to_token_stream().to_string();
+                    let key = format!("{}{}", normalize_route(&route), method);
 
-                    if handler_validation.get(&key).is_some() {
+                    if let Some(defined) = handler_validation.get(&key) {
                         return Err(SynError::new(
                             h.brace.span,
-                            "identical handler already defined",
+                            format!(
+                                "identical handler already defined: {} {} matches the same requests as {} {}",
+                                method, route, method, defined
+                            ),
                         ));
                     }
-                    handler_validation.insert(key, ());
+                    handler_validation.insert(key, route.clone());
+
+                    if let Some(name) = &h.name {
+                        if let Some(defined) = names.get(&name.value()) {
+                            return Err(SynError::new_spanned(
+                                name,
+                                format!(
+                                    "route name `{}` already used by {}",
+                                    name.value(),
+                                    defined
+                                ),
+                            ));
+                        }
+                        if route.trim_matches('"').ends_with('*') {
+                            return Err(SynError::new_spanned(
+                                name,
+                                "a route ending with `*` cannot be named",
+                            ));
+                        }
+                        // the name is part of the generated `{name}_url` function
+                        if syn::parse_str::<Ident>(&name.value()).is_err() {
+                            return Err(SynError::new_spanned(
+                                name,
+                                format!("route name `{}` is not a valid identifier", name.value()),
+                            ));
+                        }
+                        names.insert(name.value(), route);
+                    }
                 }
 
                 handlers = Some(h);
@@ -780,7 +1626,7 @@ impl Parse for Config {
             return Err(Error::new_spanned(
                 key.clone(),
                 format!(
-                    "unknown key: `{}`. Only `route`, `handler` and `method` are allowed",
+                    "unknown key: `{}`. Only `address`, `container`, `tls`, `jobs`, `middleware`, `openapi`, `routing`, `fallback`, `error_renderer` and `handlers` are allowed",
                     key
                 ),
             ));
@@ -796,12 +1642,33 @@ impl Parse for Config {
             None => return Err(SynError::new(Span::call_site(), "missing `handlers`")),
         };
 
+        // the document is served ahead of the router and would shadow the handler
+        if let Some(openapi) = &openapi {
+            let doc_route = openapi.route.value();
+            for h in &handlers {
+                if let Lit::Str(route) = &h.route.lit {
+                    if route.value() == doc_route {
+                        return Err(SynError::new_spanned(
+                            &h.route,
+                            format!("the openapi document is already served at `{}`", doc_route),
+                        ));
+                    }
+                }
+            }
+        }
+
         return Ok(Config {
             address,
             container,
+            tls,
             jobs,
             middleware,
             handlers,
+            scopes,
+            fallback,
+            error_renderer,
+            routing,
+            openapi,
         });
     }
 }
@@ -811,7 +1678,9 @@ pub(crate) struct Handler {
     brace: token::Brace,
     route: ExprLit,
     method: ExprPath,
-    handler: ExprPath,
+    handler: Expr,
+    name: Option<LitStr>,
+    scopes: Vec<usize>,
 }
 
 impl Parse for Handler {
@@ -820,7 +1689,8 @@ impl Parse for Handler {
         let brace = braced!(content in input);
         let mut route: Option<ExprLit> = None;
         let mut method: Option<ExprPath> = None;
-        let mut handler: Option<ExprPath> = None;
+        let mut handler: Option<Expr> = None;
+        let mut name: Option<LitStr> = None;
 
         while !content.is_empty() {
             if content.peek(token::Comma) {
@@ -840,15 +1710,21 @@ impl Parse for Handler {
                 continue;
             }
             if key == "handler" {
-                let h: ExprPath = content.parse()?;
+                // a handler or an expression that returns one, like `serve_dir("./public")`
+                let h: Expr = content.parse()?;
                 handler = Some(h);
                 continue;
             }
+            if key == "name" {
+                let n: LitStr = content.parse()?;
+                name = Some(n);
+                continue;
+            }
 
             return Err(Error::new_spanned(
                 key.clone(),
                 format!(
-                    "unknown key: `{}`. Only `route`, `handler` and `method` are allowed",
+                    "unknown key: `{}`. Only `route`, `handler`, `method` and `name` are allowed",
                     key
                 ),
             ));
@@ -891,11 +1767,191 @@ impl Parse for Handler {
             None => return Err(SynError::new(brace.span, "missing `handler`")),
         };
 
+        // the `{name}_url` function is defined on the handler type
+        if let (Some(name), false) = (&name, matches!(handler, Expr::Path(_))) {
+            return Err(SynError::new_spanned(
+                &handler,
+                format!(
+                    "route `{}` is named but its handler is an expression, only handler types can be named",
+                    name.value()
+                ),
+            ));
+        }
+
         return Ok(Handler {
             brace,
             route,
             method,
             handler,
+            name,
+            scopes: vec![],
         });
     }
 }
+
+#[derive(Debug)]
+pub(crate) struct Scope {
+    pub middleware: Option<ReqResArray>,
+    pub jobs: Option<ReqResArray>,
+}
+
+/// an element of a `handlers` list, either a handler or a nested scope
+#[derive(Debug)]
+pub(crate) enum Entry {
+    Handler(Handler),
+    Scope {
+        prefix: LitStr,
+        scope: Scope,
+        entries: Punctuated<Entry, token::Comma>,
+    },
+}
+
+impl Entry {
+    /// looks ahead for a `scope` key without consuming the input
+    fn is_scope(input: ParseStream) -> bool {
+        let scan = || -> SynResult<bool> {
+            let fork = input.fork();
+            let content;
+            let _ = braced!(content in fork);
+
+            while !content.is_empty() {
+                if content.peek(token::Comma) {
+                    let _: token::Comma = content.parse()?;
+                    continue;
+                }
+                let key: Ident = content.parse()?;
+                if key == "scope" {
+                    return Ok(true);
+                }
+                while !content.is_empty() && !content.peek(token::Comma) {
+                    let _: proc_macro2::TokenTree = content.parse()?;
+                }
+            }
+            Ok(false)
+        };
+        scan().unwrap_or(false)
+    }
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        if !Self::is_scope(input) {
+            let h: Handler = input.parse()?;
+            return Ok(Entry::Handler(h));
+        }
+
+        let content;
+        let brace = braced!(content in input);
+        let mut prefix: Option<LitStr> = None;
+        let mut middleware: Option<ReqResArray> = None;
+        let mut jobs: Option<ReqResArray> = None;
+        let mut entries: Option<Punctuated<Entry, token::Comma>> = None;
+
+        while !content.is_empty() {
+            if content.peek(token::Comma) {
+                let _: token::Comma = content.parse()?;
+            }
+            if content.is_empty() {
+                break;
+            }
+
+            let key = content.fork().parse::<Ident>()?;
+
+            if key == "scope" {
+                let _: Ident = content.parse()?;
+                let _: token::Colon = content.parse()?;
+                let p: LitStr = content.parse()?;
+                if !p.value().starts_with('/') {
+                    return Err(Error::new_spanned(p, "scope prefix must start with `/`"));
+                }
+                prefix = Some(p);
+                continue;
+            }
+            if key == "middleware" {
+                let m: ReqResArray = content.parse()?;
+                middleware = Some(m);
+                continue;
+            }
+            if key == "jobs" {
+                let j: ReqResArray = content.parse()?;
+                jobs = Some(j);
+                continue;
+            }
+            if key == "handlers" {
+                let _: Ident = content.parse()?;
+                let _: token::Colon = content.parse()?;
+                let br;
+                let _ = bracketed!(br in content);
+                let e: Punctuated<Entry, token::Comma> = Punctuated::parse(&br)?;
+                entries = Some(e);
+                continue;
+            }
+
+            return Err(Error::new_spanned(
+                key.clone(),
+                format!(
+                    "unknown key: `{}`. Only `scope`, `middleware`, `jobs` and `handlers` are allowed",
+                    key
+                ),
+            ));
+        }
+
+        let prefix = match prefix {
+            Some(r) => r,
+            None => return Err(SynError::new(brace.span, "missing `scope`")),
+        };
+
+        let entries = match entries {
+            Some(r) => r,
+            None => return Err(SynError::new(brace.span, "missing `handlers`")),
+        };
+
+        if entries.is_empty() {
+            return Err(SynError::new(brace.span, "no handlers registered"));
+        }
+
+        Ok(Entry::Scope {
+            prefix,
+            scope: Scope { middleware, jobs },
+            entries,
+        })
+    }
+}
+
+/// expands the scopes into a flat list of handlers
+/// with their routes prefixed and their scope chain recorded
+fn flatten(
+    entries: Punctuated<Entry, token::Comma>,
+    prefix: &str,
+    chain: &[usize],
+    scopes: &mut Vec<Scope>,
+    handlers: &mut Vec<Handler>,
+) {
+    for entry in entries {
+        match entry {
+            Entry::Handler(mut h) => {
+                if !prefix.is_empty() {
+                    let route = h.route.to_token_stream().to_string();
+                    let route = format!("{}{}", prefix, route.trim_matches('"'));
+                    h.route = ExprLit {
+                        attrs: vec![],
+                        lit: Lit::Str(LitStr::new(&route, h.route.lit.span())),
+                    };
+                }
+                h.scopes = chain.to_vec();
+                handlers.push(h);
+            }
+            Entry::Scope {
+                prefix: scope_prefix,
+                scope,
+                entries,
+            } => {
+                let prefix = format!("{}{}", prefix, scope_prefix.value().trim_end_matches('/'));
+                let mut chain = chain.to_vec();
+                chain.push(scopes.len());
+                scopes.push(scope);
+                flatten(entries, &prefix, &chain, scopes, handlers);
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::app::{Func, ReqResArray};
+use crate::openapi;
 use proc_macro::TokenStream;
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2, TokenTree};
 use quote::ToTokens;
 use quote::{format_ident, quote};
 use std::collections::HashMap;
@@ -8,7 +9,7 @@ use syn::parse::Parse;
 use syn::punctuated::Punctuated;
 use syn::{
     braced, parse::ParseStream, parse_macro_input, token, Error, Expr, ExprLit, FnArg, ItemFn,
-    PatType, Path, PathSegment, Result as SynResult, Type, TypePath,
+    PatType, Path, PathSegment, Result as SynResult, ReturnType, Type, TypePath,
 };
 
 pub(crate) const HAS_PATH_ARGS_PREFIX: &str = "HasPathArgs";
@@ -40,6 +41,20 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
         let args = parse_macro_input!(args as Config);
         Some(args)
     };
+    let openapi = args.as_ref().and_then(|a| a.openapi.as_ref()).map(|o| {
+        let summary = match &o.summary {
+            Some(s) => quote! {Some(#s)},
+            None => quote! {None},
+        };
+        let response = match &o.response {
+            Some(r) => {
+                let content = openapi::make_content(r);
+                quote! {Some(#content)}
+            }
+            None => quote! {None},
+        };
+        (summary, response)
+    });
     let ArgsCall {
         middleware_call,
         job_call,
@@ -59,6 +74,8 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
     let mut consumed = None;
     let mut last_args = vec![];
     let mut path_ident = None;
+    let mut query_schema = quote! {None};
+    let mut body_schema = quote! {None};
 
     for arg in func.sig.inputs.iter() {
         if let FnArg::Typed(tp) = arg {
@@ -91,10 +108,14 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
                     }
                     consumed = Some(arg.clone());
                     allowed_query = false;
+                    let schema = openapi::make_schema_of(&tp.ty);
+                    query_schema = quote! {Some(#schema)};
                     make_args.push(ts);
                     give_args.push(quote! {#i});
                 }
-                HandlerArgs::Parts(i, ts) => {
+                HandlerArgs::Parts(i, ts)
+                | HandlerArgs::Header(i, ts)
+                | HandlerArgs::Cookies(i, ts) => {
                     consumed = Some(arg.clone());
                     last_args.push(ts);
                     give_args.push(quote! {#i});
@@ -110,6 +131,10 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
                         .into();
                     }
                     allowed_body = false;
+                    if let Type::Path(body) = &*tp.ty {
+                        let content = openapi::make_content(body);
+                        body_schema = quote! {Some(#content)};
+                    }
                     last_args.push(ts);
                     give_args.push(quote! {#i});
                 }
@@ -175,6 +200,37 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
 
     if !is_request {
         make_args.push(quote! {let (parts, body) = args.request.into_parts();});
+    } else {
+        // the request is moved into the handler, a `RequestResponder` gets a copy of its head
+        // an opaque return type only dispatches to it when it names `RequestResponder`
+        let needs_parts = match &func.sig.output {
+            ReturnType::Type(_, ttype) if !is_opaque(ttype) => quote! {
+                (&mut darpi::response::Respond::<#ttype>(None)).needs_parts()
+            },
+            ReturnType::Type(_, ttype) => {
+                let named = ttype
+                    .to_token_stream()
+                    .to_string()
+                    .contains("RequestResponder");
+                quote! {#named}
+            }
+            ReturnType::Default => quote! {false},
+        };
+        make_args.insert(
+            0,
+            quote! {
+                let parts = {
+                    let (mut parts, _) = darpi::Request::new(()).into_parts();
+                    if #needs_parts {
+                        parts.method = args.request.method().clone();
+                        parts.uri = args.request.uri().clone();
+                        parts.version = args.request.version();
+                        parts.headers = args.request.headers().clone();
+                    }
+                    parts
+                };
+            },
+        );
     }
     make_args.append(&mut last_args);
 
@@ -203,6 +259,30 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
     let jobs_req = job_call.req;
     let jobs_res = job_call.res;
 
+    let operation = match openapi {
+        Some((summary, response)) => {
+            let path_schema = match &path_ident {
+                Some(p) => quote! {Some(<#p as darpi::openapi::Schema>::schema())},
+                None => quote! {None},
+            };
+            quote! {
+                darpi::openapi::Operation {
+                    summary: #summary,
+                    path: #path_schema,
+                    query: #query_schema,
+                    body: #body_schema,
+                    response: #response,
+                }
+            }
+        }
+        None => quote! {darpi::openapi::Operation::default()},
+    };
+
+    let route_path = match &path_ident {
+        Some(p) => quote! {#p},
+        None => quote! {()},
+    };
+
     let output = quote! {
         #[allow(non_camel_case_types, missing_docs)]
         trait #has_path_args {}
@@ -214,6 +294,16 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
            #func_copy
         }
 
+        impl darpi::openapi::Describe for #func_name {
+            fn operation() -> darpi::openapi::Operation {
+                #operation
+            }
+        }
+
+        impl darpi::request::RoutePath for #func_name {
+            type Path = #route_path;
+        }
+
         #[darpi::async_trait]
         impl<#dummy_t #a_gen_impl> darpi::Handler<#module_type #a_gen> for #func_name #dummy_where {
             async fn call(self, mut args: darpi::Args<#module_type #a_gen>) -> Result<darpi::Response<darpi::Body>, std::convert::Infallible> {
@@ -224,6 +314,7 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
                use darpi::request::FromQuery;
                use darpi::request::FromRequestBodyWithContainer;
                use darpi::response::ResponderError;
+               use darpi::response::{ViaRequestResponder, ViaResponder};
                #[allow(unused_imports)]
                use darpi::RequestMiddleware;
                #[allow(unused_imports)]
@@ -237,7 +328,7 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
 
                #(#make_args )*
 
-               let mut rb = Self::#func_name(#(#give_args ,)*).await.respond();
+               let mut rb = (&mut darpi::response::Respond(Some(Self::#func_name(#(#give_args ,)*).await))).respond_with(&parts);
 
                #(#middleware_res )*
                #(#jobs_res )*
@@ -249,6 +340,18 @@ pub(crate) fn make_handler(args: TokenStream, input: TokenStream) -> TokenStream
     output.into()
 }
 
+/// `impl Trait` cannot be named outside of the function that returns it
+fn is_opaque(ttype: &Type) -> bool {
+    fn has_impl(ts: TokenStream2) -> bool {
+        ts.into_iter().any(|tt| match tt {
+            TokenTree::Ident(ident) => ident == "impl",
+            TokenTree::Group(group) => has_impl(group.stream()),
+            _ => false,
+        })
+    }
+    has_impl(ttype.to_token_stream())
+}
+
 struct ArgsCall {
     middleware_call: MiddlewareCall,
     job_call: JobCall,
@@ -371,6 +474,21 @@ fn make_query(
     }
 }
 
+fn make_header(arg_name: &Ident, full: &TypePath) -> proc_macro2::TokenStream {
+    quote! {
+        let #arg_name: #full = match <#full as darpi::request::FromHeader>::from_header(&parts.headers) {
+            Ok(h) => h,
+            Err(e) => return Ok(darpi::request::assert_respond_err::<#full, darpi::request::HeaderError>(e))
+        };
+    }
+}
+
+fn make_cookies(arg_name: &Ident, full: &TypePath) -> proc_macro2::TokenStream {
+    quote! {
+        let #arg_name: #full = darpi::cookie::CookieJar::from_headers(&parts.headers);
+    }
+}
+
 fn make_path_args(arg_name: &Ident, last: &PathSegment) -> proc_macro2::TokenStream {
     quote! {
         let #arg_name = match #last::try_from(args.route_args) {
@@ -423,6 +541,8 @@ enum HandlerArgs {
     Module(Ident, proc_macro2::TokenStream),
     Middleware(Ident, proc_macro2::TokenStream, u64, Type),
     Parts(Ident, proc_macro2::TokenStream),
+    Header(Ident, proc_macro2::TokenStream),
+    Cookies(Ident, proc_macro2::TokenStream),
     Request(Ident, proc_macro2::TokenStream),
 }
 
@@ -508,6 +628,16 @@ fn make_handler_args(
                 return Ok(HandlerArgs::Query(arg_name, res));
             }
 
+            if attr_ident == "header" {
+                let res = make_header(&arg_name, &tp);
+                return Ok(HandlerArgs::Header(arg_name, res));
+            }
+
+            if attr_ident == "cookies" {
+                let res = make_cookies(&arg_name, &tp);
+                return Ok(HandlerArgs::Cookies(arg_name, res));
+            }
+
             if attr_ident == "body" {
                 let res = make_json_body(&arg_name, &tp, &module_ident);
                 return Ok(HandlerArgs::Body(arg_name, res));
@@ -722,7 +852,7 @@ fn make_call_middleware(middleware: Option<ReqResArray>) -> MiddlewareCall {
                 let r_m_arg_ident = format_ident!("res_m_arg_{}", i);
                 let mut sorter = 0_u16;
                 let m_args: Vec<proc_macro2::TokenStream> =
-                    get_res_middleware_arg(e, &mut sorter, rm.len(), res_len);
+                    get_res_middleware_arg(e, &mut sorter, rm.len(), req_len);
 
                 let m_args = if m_args.len() > 1 {
                     quote! {(#(#m_args ,)*)}
@@ -875,6 +1005,7 @@ pub struct Config {
     pub(crate) container: Option<syn::Path>,
     pub(crate) jobs: Option<ReqResArray>,
     pub(crate) middleware: Option<ReqResArray>,
+    pub(crate) openapi: Option<openapi::Config>,
 }
 
 impl Parse for Config {
@@ -885,6 +1016,7 @@ impl Parse for Config {
         let mut container: Option<syn::Path> = None;
         let mut jobs: Option<ReqResArray> = None;
         let mut middleware: Option<ReqResArray> = None;
+        let mut openapi: Option<openapi::Config> = None;
 
         while !content.is_empty() {
             if content.peek(token::Comma) {
@@ -910,6 +1042,11 @@ impl Parse for Config {
                 middleware = Some(m);
                 continue;
             }
+            if key == "openapi" {
+                let o: openapi::Config = content.parse()?;
+                openapi = Some(o);
+                continue;
+            }
 
             return Err(Error::new_spanned(
                 key.clone(),
@@ -924,6 +1061,7 @@ impl Parse for Config {
             container,
             jobs,
             middleware,
+            openapi,
         });
     }
 }
@@ -6,7 +6,9 @@ mod job;
 mod logger;
 mod main_macro;
 mod middleware;
+mod openapi;
 mod request;
+mod response;
 
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
@@ -33,6 +35,41 @@ pub fn query(input: TokenStream) -> TokenStream {
     request::make_query_type(input)
 }
 
+#[proc_macro_derive(MultipartFields)]
+pub fn multipart_fields(input: TokenStream) -> TokenStream {
+    request::make_multipart_fields_type(input)
+}
+
+/// implements `ResponderError` with a status code, public message and headers per variant
+/// every variant needs a `#[status]`, its own or the one of the type
+/// the `Display` text is the detail of the problem, unless `#[message]` replaces it
+/// in that case the `Display` text is only logged
+/// ```rust,ignore
+/// #[derive(Debug, Display, ResponderError)]
+/// #[problem(type = "https://example.com/probs/auth")]
+/// pub enum AuthError {
+///     #[status(401)]
+///     #[header("www-authenticate", "Bearer")]
+///     #[display(fmt = "missing token")]
+///     MissingToken,
+///     #[status(403)]
+///     #[display(fmt = "user {} lacks the {} role", _0, _1)]
+///     #[message("no permission")]
+///     Forbidden(String, String),
+/// }
+/// ```
+#[proc_macro_derive(ResponderError, attributes(status, message, header, problem))]
+pub fn responder_error(input: TokenStream) -> TokenStream {
+    response::make_responder_error(input)
+}
+
+/// `#[serde]` attributes that rename, skip, flatten or default fields are reflected in the schema
+/// the ones that change the shape in other ways, like `tag` or `untagged`, are a compile error
+#[proc_macro_derive(Schema, attributes(serde))]
+pub fn schema(input: TokenStream) -> TokenStream {
+    openapi::make_schema(input)
+}
+
 #[proc_macro_attribute]
 pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
     handler::make_handler(args, input)
@@ -0,0 +1,384 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    braced, parse_macro_input, parse_quote, token, Attribute, Data, DeriveInput, Error, Fields,
+    GenericArgument, Ident, Lit, LitStr, Meta, MetaNameValue, NestedMeta, PathArguments,
+    Result as SynResult, Type, TypePath,
+};
+
+pub(crate) fn make_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match schema_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn schema_impl(input: &DeriveInput) -> SynResult<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let container = SerdeAttrs::parse(&input.attrs)?;
+
+    let schema = match &input.data {
+        Data::Struct(data) => {
+            let named = match &data.fields {
+                Fields::Named(named) => named,
+                _ => {
+                    return Err(Error::new_spanned(
+                        name,
+                        "Only structs with named fields are supported",
+                    ))
+                }
+            };
+
+            let mut fields = vec![];
+            for field in named.named.iter() {
+                let attrs = SerdeAttrs::parse(&field.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+
+                let ttype = &field.ty;
+                if attrs.flatten {
+                    fields.push(quote! {
+                        let inner = <#ttype as darpi::openapi::Schema>::schema();
+                        if let Some(inner) = inner["properties"].as_object() {
+                            properties.extend(inner.clone());
+                        }
+                        if let Some(inner) = inner["required"].as_array() {
+                            required.extend(inner.iter().cloned());
+                        }
+                    });
+                    continue;
+                }
+
+                let field_name = match attrs.rename {
+                    Some(rename) => rename,
+                    None => {
+                        let ident = field.ident.as_ref().unwrap().to_string();
+                        let ident = ident.trim_start_matches("r#");
+                        match &container.rename_all {
+                            Some(rule) => rule.apply_to_field(ident),
+                            None => ident.to_string(),
+                        }
+                    }
+                };
+                // a field with a default can be left out by the client
+                let optional = attrs.default || container.default;
+                fields.push(quote! {
+                    properties.insert(#field_name.to_string(), <#ttype as darpi::openapi::Schema>::schema());
+                    if !#optional && <#ttype as darpi::openapi::Schema>::required() {
+                        required.push(darpi::serde_json::Value::from(#field_name));
+                    }
+                });
+            }
+
+            quote! {
+                let mut properties = darpi::serde_json::Map::new();
+                let mut required: Vec<darpi::serde_json::Value> = vec![];
+                #(#fields )*
+                darpi::serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                })
+            }
+        }
+        Data::Enum(data) => {
+            let mut variants = vec![];
+            for v in data.variants.iter() {
+                if !v.fields.is_empty() {
+                    return Err(Error::new_spanned(
+                        v,
+                        "Only enums without fields are supported",
+                    ));
+                }
+
+                let attrs = SerdeAttrs::parse(&v.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                variants.push(match attrs.rename {
+                    Some(rename) => rename,
+                    None => match &container.rename_all {
+                        Some(rule) => rule.apply_to_variant(&v.ident.to_string()),
+                        None => v.ident.to_string(),
+                    },
+                });
+            }
+
+            quote! {
+                darpi::serde_json::json!({
+                    "type": "string",
+                    "enum": [#(#variants ,)*],
+                })
+            }
+        }
+        Data::Union(_) => return Err(Error::new_spanned(name, "Unions are not supported")),
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(darpi::openapi::Schema));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics darpi::openapi::Schema for #name #ty_generics #where_clause {
+            fn schema() -> darpi::serde_json::Value {
+                #schema
+            }
+        }
+    })
+}
+
+/// the `#[serde]` attributes that change the shape of the serialized type
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    skip: bool,
+    flatten: bool,
+    default: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> SynResult<Self> {
+        let mut serde = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path.is_ident("serde")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                other => return Err(Error::new_spanned(other, "expected `#[serde(...)]`")),
+            };
+
+            for nested in list.nested.iter() {
+                let meta = match nested {
+                    NestedMeta::Meta(meta) => meta,
+                    NestedMeta::Lit(lit) => {
+                        return Err(Error::new_spanned(lit, "expected a serde attribute"))
+                    }
+                };
+                let key = meta
+                    .path()
+                    .get_ident()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default();
+
+                match (key.as_str(), meta) {
+                    (
+                        "rename",
+                        Meta::NameValue(MetaNameValue {
+                            lit: Lit::Str(s), ..
+                        }),
+                    ) => {
+                        serde.rename = Some(s.value());
+                    }
+                    (
+                        "rename_all",
+                        Meta::NameValue(MetaNameValue {
+                            lit: Lit::Str(s), ..
+                        }),
+                    ) => {
+                        serde.rename_all = Some(RenameRule::parse(s)?);
+                    }
+                    ("skip", Meta::Path(_)) => serde.skip = true,
+                    ("flatten", Meta::Path(_)) => serde.flatten = true,
+                    ("default", _)
+                    | ("skip_serializing", _)
+                    | ("skip_deserializing", _)
+                    | ("skip_serializing_if", _) => serde.default = true,
+                    // these do not change the schema
+                    ("alias", _)
+                    | ("deny_unknown_fields", _)
+                    | ("crate", _)
+                    | ("bound", _)
+                    | ("serialize_with", _)
+                    | ("deserialize_with", _)
+                    | ("with", _) => {}
+                    _ => {
+                        return Err(Error::new_spanned(
+                            meta,
+                            format!(
+                                "`#[serde({})]` is not supported by `#[derive(Schema)]`",
+                                key
+                            ),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(serde)
+    }
+}
+
+/// the `#[serde(rename_all)]` case conventions
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(s: &LitStr) -> SynResult<Self> {
+        Ok(match s.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            other => {
+                return Err(Error::new_spanned(
+                    s,
+                    format!("unknown rename rule `{}`", other),
+                ))
+            }
+        })
+    }
+
+    /// fields are written in snake_case
+    fn apply_to_field(&self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                        None => String::new(),
+                    }
+                })
+                .collect(),
+            RenameRule::Camel => lower_first(&RenameRule::Pascal.apply_to_field(field)),
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    /// variants are written in PascalCase
+    fn apply_to_variant(&self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lower_first(variant),
+            _ => {
+                let mut snake = String::new();
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+                self.apply_to_field(&snake)
+            }
+        }
+    }
+}
+
+fn lower_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// the `openapi` key of `#[handler]`
+#[derive(Debug)]
+pub struct Config {
+    pub(crate) summary: Option<LitStr>,
+    pub(crate) response: Option<TypePath>,
+}
+
+impl Parse for Config {
+    fn parse(input: ParseStream) -> SynResult<Self> {
+        let _: Ident = input.parse()?;
+        let _: token::Colon = input.parse()?;
+
+        let content;
+        let _ = braced!(content in input);
+
+        let mut summary = None;
+        let mut response = None;
+
+        while !content.is_empty() {
+            if content.peek(token::Comma) {
+                let _: token::Comma = content.parse()?;
+            }
+            if content.is_empty() {
+                break;
+            }
+
+            let key: Ident = content.parse()?;
+            let _: token::Colon = content.parse()?;
+
+            if key == "summary" {
+                let s: LitStr = content.parse()?;
+                summary = Some(s);
+                continue;
+            }
+            if key == "response" {
+                let r: TypePath = content.parse()?;
+                response = Some(r);
+                continue;
+            }
+
+            return Err(Error::new_spanned(
+                key.clone(),
+                format!(
+                    "unknown key: `{}`. Only `summary` and `response` are allowed",
+                    key
+                ),
+            ));
+        }
+
+        Ok(Config { summary, response })
+    }
+}
+
+/// `Json<T>` is described by the schema of `T` and the content type of `Json`
+pub(crate) fn make_content(tp: &TypePath) -> proc_macro2::TokenStream {
+    let last = tp.path.segments.last().expect("no type");
+    let format = last.ident.to_string();
+
+    if let PathArguments::AngleBracketed(ab) = &last.arguments {
+        if let Some(GenericArgument::Type(inner)) = ab.args.first() {
+            return quote! {
+                (darpi::openapi::content_type(#format), <#inner as darpi::openapi::Schema>::schema())
+            };
+        }
+    }
+
+    quote! {
+        (darpi::openapi::content_type(#format), <#tp as darpi::openapi::Schema>::schema())
+    }
+}
+
+/// `Option<T>` is described by the schema of `T`
+pub(crate) fn make_schema_of(ttype: &Type) -> proc_macro2::TokenStream {
+    if let Type::Path(tp) = ttype {
+        let last = tp.path.segments.last().expect("no type");
+        if last.ident == "Option" {
+            if let PathArguments::AngleBracketed(ab) = &last.arguments {
+                if let Some(GenericArgument::Type(inner)) = ab.args.first() {
+                    return quote! {{
+                        let mut schema = <#inner as darpi::openapi::Schema>::schema();
+                        schema["required"] = darpi::serde_json::json!([]);
+                        schema
+                    }};
+                }
+            }
+        }
+    }
+    quote! {<#ttype as darpi::openapi::Schema>::schema()}
+}
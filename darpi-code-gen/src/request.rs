@@ -1,5 +1,5 @@
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote, ToTokens};
 use syn::{parse_macro_input, Error, Fields, ItemStruct};
 
 pub(crate) fn make_path_type(input: TokenStream) -> TokenStream {
@@ -16,6 +16,8 @@ pub(crate) fn make_path_type(input: TokenStream) -> TokenStream {
         let mut fields_create = vec![];
         let mut fields = vec![];
         let mut strings = vec![];
+        let mut path_args = vec![];
+        let mut route_args = vec![];
 
         let mut i = 0;
         let mut sorted_fields = Vec::with_capacity(named.named.len());
@@ -45,6 +47,9 @@ pub(crate) fn make_path_type(input: TokenStream) -> TokenStream {
             };
 
             fields_create.push(q);
+            let name_str = name.to_string();
+            path_args.push(quote! {(#name_str, self.#name.to_string())});
+            route_args.push(format_ident!("route_arg_{}", name));
             fields.push(name.to_token_stream());
             strings.push(quote! {String});
             i += 1;
@@ -60,6 +65,19 @@ pub(crate) fn make_path_type(input: TokenStream) -> TokenStream {
                 }
             }
 
+            // checked by `app!` against the arguments of named routes
+            #[doc(hidden)]
+            #[allow(non_upper_case_globals)]
+            impl #name {
+                #(pub const #route_args: () = ();)*
+            }
+
+            impl darpi::request::ToPath for #name {
+                fn to_path_args(&self) -> Vec<(&'static str, String)> {
+                    vec![#(#path_args ,)*]
+                }
+            }
+
             impl darpi::response::ErrResponder<darpi::request::PathError, darpi::Body> for #name {
                 #[cold]
                 fn respond_err(e: darpi::request::PathError) -> darpi::Response<darpi::Body> {
@@ -68,10 +86,9 @@ pub(crate) fn make_path_type(input: TokenStream) -> TokenStream {
                         darpi::request::PathError::Missing(msg) => msg,
                     };
 
-                    darpi::Response::builder()
-                        .status(darpi::StatusCode::BAD_REQUEST)
-                        .body(darpi::Body::from(msg))
-                        .expect("this not to happen!")
+                    darpi::response::ResponderError::respond_err(
+                        &darpi::problem::Problem::new(darpi::StatusCode::BAD_REQUEST).detail(msg),
+                    )
                 }
             }
         };
@@ -96,10 +113,67 @@ pub(crate) fn make_query_type(input: TokenStream) -> TokenStream {
                     darpi::request::QueryPayloadError::NotExist => "missing query params".to_string(),
                 };
 
-                darpi::Response::builder()
-                    .status(darpi::StatusCode::BAD_REQUEST)
-                    .body(darpi::Body::from(msg))
-                    .expect("this not to happen!")
+                darpi::response::ResponderError::respond_err(
+                    &darpi::problem::Problem::new(darpi::StatusCode::BAD_REQUEST).detail(msg),
+                )
+            }
+        }
+    };
+    tokens.into()
+}
+
+pub(crate) fn make_multipart_fields_type(input: TokenStream) -> TokenStream {
+    let struct_arg = parse_macro_input!(input as ItemStruct);
+    let name = &struct_arg.ident;
+
+    let named = match &struct_arg.fields {
+        Fields::Named(named) => named,
+        _ => {
+            return Error::new_spanned(struct_arg, "Only structs with named fields are supported")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let mut fields_create = vec![];
+    let mut fields = vec![];
+
+    for field in named.named.iter() {
+        let ident = field.ident.as_ref().expect("named field");
+        let ident_str = ident.to_string();
+        let ttype = &field.ty;
+
+        // `Option<T>` fields can be missing
+        let optional = match ttype {
+            syn::Type::Path(tp) => tp.path.segments.last().and_then(|s| {
+                if s.ident != "Option" {
+                    return None;
+                }
+                match &s.arguments {
+                    syn::PathArguments::AngleBracketed(ab) => ab.args.first().cloned(),
+                    _ => None,
+                }
+            }),
+            _ => None,
+        };
+
+        let q = match optional {
+            Some(inner) => quote! {
+                let #ident = darpi::multipart::optional_field::<#inner>(fields, #ident_str)?;
+            },
+            None => quote! {
+                let #ident = darpi::multipart::field::<#ttype>(fields, #ident_str)?;
+            },
+        };
+        fields_create.push(q);
+        fields.push(ident.to_token_stream());
+    }
+
+    let tokens = quote! {
+        impl darpi::multipart::FromFields for #name {
+            fn from_fields(fields: &[(String, String)]) -> Result<Self, darpi::multipart::MultipartErr> {
+                #(#fields_create)*
+                Ok(Self{#(#fields ,)*})
             }
         }
     };
@@ -0,0 +1,212 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, token, Attribute, Data, DeriveInput, Error, LitInt, LitStr, Meta,
+    NestedMeta, Result as SynResult,
+};
+
+/// the attributes of the type or of a single variant
+#[derive(Clone, Default)]
+struct ErrorAttrs {
+    status: Option<u16>,
+    message: Option<LitStr>,
+    problem_type: Option<LitStr>,
+    title: Option<LitStr>,
+    headers: Vec<(String, LitStr)>,
+}
+
+impl ErrorAttrs {
+    fn parse(attrs: &[Attribute]) -> SynResult<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs {
+            if attr.path.is_ident("status") {
+                let lit: LitInt = attr.parse_args()?;
+                let code: u16 = lit.base10_parse()?;
+                if http::StatusCode::from_u16(code).is_err() {
+                    return Err(Error::new_spanned(lit, "invalid status code"));
+                }
+                parsed.status = Some(code);
+            } else if attr.path.is_ident("message") {
+                parsed.message = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("header") {
+                let args: Punctuated<LitStr, token::Comma> =
+                    attr.parse_args_with(Punctuated::parse_terminated)?;
+                if args.len() != 2 {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "expected `#[header(\"name\", \"value\")]`",
+                    ));
+                }
+                let name = args[0].value().to_lowercase();
+                if http::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    return Err(Error::new_spanned(&args[0], "invalid header name"));
+                }
+                let value = args[1].value();
+                if !value.bytes().all(|b| (32..127).contains(&b) || b == b'\t') {
+                    return Err(Error::new_spanned(&args[1], "invalid header value"));
+                }
+                parsed.headers.push((name, args[1].clone()));
+            } else if attr.path.is_ident("problem") {
+                let list = match attr.parse_meta()? {
+                    Meta::List(list) => list,
+                    meta => {
+                        return Err(Error::new_spanned(
+                            meta,
+                            "expected `#[problem(type = \"...\", title = \"...\")]`",
+                        ))
+                    }
+                };
+                for nested in list.nested {
+                    let nv = match nested {
+                        NestedMeta::Meta(Meta::NameValue(nv)) => nv,
+                        nested => {
+                            return Err(Error::new_spanned(nested, "expected `key = \"value\"`"))
+                        }
+                    };
+                    let value = match &nv.lit {
+                        syn::Lit::Str(s) => s.clone(),
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    };
+                    if nv.path.is_ident("type") {
+                        parsed.problem_type = Some(value);
+                    } else if nv.path.is_ident("title") {
+                        parsed.title = Some(value);
+                    } else {
+                        return Err(Error::new_spanned(
+                            nv.path,
+                            "unknown key, only `type` and `title` are allowed",
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// the variant attributes win over the ones of the type, the headers of both are sent
+    fn merge(&self, variant: ErrorAttrs) -> Self {
+        let mut headers = self.headers.clone();
+        headers.extend(variant.headers);
+        Self {
+            status: variant.status.or(self.status),
+            message: variant.message.or_else(|| self.message.clone()),
+            problem_type: variant.problem_type.or_else(|| self.problem_type.clone()),
+            title: variant.title.or_else(|| self.title.clone()),
+            headers,
+        }
+    }
+
+    /// every variant needs a status, there is no default
+    fn status_code<T: ToTokens>(&self, target: T) -> SynResult<TokenStream2> {
+        let code = match self.status {
+            Some(code) => code,
+            None => {
+                return Err(Error::new_spanned(
+                    target,
+                    "missing `#[status]`, add it to the variant or to the type",
+                ))
+            }
+        };
+        Ok(quote! {darpi::StatusCode::from_u16(#code).expect("this cannot happen")})
+    }
+
+    fn problem(&self) -> TokenStream2 {
+        // the public message replaces the `Display` text, which is only logged
+        let mut problem = match &self.message {
+            Some(message) => quote! {{
+                darpi::log::error!("{}", self);
+                problem.detail(#message)
+            }},
+            None => quote! {problem.detail(self.to_string())},
+        };
+        if let Some(problem_type) = &self.problem_type {
+            problem = quote! {#problem.problem_type(#problem_type)};
+        }
+        if let Some(title) = &self.title {
+            problem = quote! {#problem.title(#title)};
+        }
+        problem
+    }
+
+    fn headers(&self) -> TokenStream2 {
+        let headers = self.headers.iter().map(|(name, value)| {
+            quote! {
+                headers.append(
+                    darpi::header::HeaderName::from_static(#name),
+                    darpi::header::HeaderValue::from_static(#value),
+                );
+            }
+        });
+        quote! {#(#headers)*}
+    }
+}
+
+pub(crate) fn make_responder_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match make_impl(input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn make_impl(input: DeriveInput) -> SynResult<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let type_attrs = ErrorAttrs::parse(&input.attrs)?;
+
+    let (status_code, problem, headers) = match &input.data {
+        Data::Enum(data) => {
+            let mut status_arms = vec![];
+            let mut problem_arms = vec![];
+            let mut header_arms = vec![];
+            for variant in data.variants.iter() {
+                let attrs = type_attrs.merge(ErrorAttrs::parse(&variant.attrs)?);
+                let ident = &variant.ident;
+                let status = attrs.status_code(ident)?;
+                let problem = attrs.problem();
+                let headers = attrs.headers();
+                status_arms.push(quote! {Self::#ident { .. } => #status,});
+                problem_arms.push(quote! {Self::#ident { .. } => #problem,});
+                header_arms.push(quote! {Self::#ident { .. } => {#headers}});
+            }
+            (
+                quote! {match *self { #(#status_arms)* }},
+                quote! {match *self { #(#problem_arms)* }},
+                quote! {match *self { #(#header_arms)* }},
+            )
+        }
+        Data::Struct(_) => (
+            type_attrs.status_code(&input.ident)?,
+            type_attrs.problem(),
+            type_attrs.headers(),
+        ),
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ResponderError cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics darpi::response::ResponderError for #name #ty_generics #where_clause {
+            fn status_code(&self) -> darpi::StatusCode {
+                #status_code
+            }
+
+            fn problem(&self) -> darpi::problem::Problem {
+                let problem = darpi::problem::Problem::new(self.status_code());
+                #problem
+            }
+
+            fn headers(&self) -> darpi::header::HeaderMap {
+                #[allow(unused_mut)]
+                let mut headers = darpi::header::HeaderMap::new();
+                #headers
+                headers
+            }
+        }
+    })
+}
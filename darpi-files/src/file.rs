@@ -0,0 +1,251 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use darpi::header::{self, HeaderMap, HeaderValue};
+use darpi::{Body, Response, StatusCode};
+use futures::stream;
+use http::response::Builder;
+use std::io::{self, SeekFrom};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// an opened file along with the validators sent to the client
+pub(crate) struct Opened {
+    file: File,
+    len: u64,
+    etag: String,
+    last_modified: Option<String>,
+    modified: Option<u64>,
+}
+
+impl Opened {
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path).await?;
+        let meta = file.metadata().await?;
+        if !meta.is_file() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "not a file"));
+        }
+
+        let len = meta.len();
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok());
+        let etag = match modified {
+            Some(m) => format!("\"{:x}-{:x}\"", len, m.as_nanos()),
+            None => format!("\"{:x}\"", len),
+        };
+        let last_modified = meta.modified().ok().map(http_date);
+
+        Ok(Self {
+            file,
+            len,
+            etag,
+            last_modified,
+            modified: modified.map(|m| m.as_secs()),
+        })
+    }
+
+    /// `If-None-Match` takes precedence over `If-Modified-Since`
+    fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(inm) = headers.get(header::IF_NONE_MATCH) {
+            let inm = inm.to_str().unwrap_or_default();
+            return inm.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == self.etag
+            });
+        }
+
+        let since = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|hv| hv.to_str().ok())
+            .and_then(|hv| DateTime::parse_from_rfc2822(hv).ok());
+        match (since, self.modified) {
+            (Some(since), Some(modified)) => modified as i64 <= since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// a `Range` is ignored when `If-Range` does not match the current file
+    fn range(&self, headers: &HeaderMap) -> Range {
+        let range = match headers.get(header::RANGE).and_then(|hv| hv.to_str().ok()) {
+            Some(range) => range,
+            None => return Range::Full,
+        };
+
+        if let Some(if_range) = headers
+            .get(header::IF_RANGE)
+            .and_then(|hv| hv.to_str().ok())
+        {
+            let matches = if_range == self.etag || Some(if_range) == self.last_modified.as_deref();
+            if !matches {
+                return Range::Full;
+            }
+        }
+
+        Range::parse(range, self.len)
+    }
+
+    /// builds the response for a `GET` or `HEAD` request
+    /// `rb` carries the headers that are specific to the caller, like the content type
+    pub fn respond(self, headers: &HeaderMap, mut rb: Builder) -> Response<Body> {
+        rb = rb.header(header::ETAG, &self.etag);
+        if let Some(lm) = &self.last_modified {
+            rb = rb.header(header::LAST_MODIFIED, lm);
+        }
+
+        if self.is_not_modified(headers) {
+            return rb
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .expect("this cannot happen");
+        }
+
+        rb = rb.header(header::ACCEPT_RANGES, "bytes");
+        match self.range(headers) {
+            Range::Full => rb
+                .header(header::CONTENT_LENGTH, self.len)
+                .body(file_body(self.file, 0, self.len)),
+            Range::Partial(start, end) => rb
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, self.len),
+                )
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .body(file_body(self.file, start, end - start + 1)),
+            Range::Unsatisfiable => rb
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", self.len))
+                .body(Body::empty()),
+        }
+        .expect("this cannot happen")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Range {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl Range {
+    /// only single ranges are supported, the whole file is sent for the rest
+    fn parse(range: &str, len: u64) -> Self {
+        let spec = match range.trim().strip_prefix("bytes=") {
+            Some(spec) if !spec.contains(',') => spec.trim(),
+            _ => return Range::Full,
+        };
+        let (start, end) = match spec.find('-') {
+            Some(i) => (spec[..i].trim(), spec[i + 1..].trim()),
+            None => return Range::Full,
+        };
+
+        if start.is_empty() {
+            // the last `n` bytes
+            return match end.parse::<u64>() {
+                Ok(0) => Range::Unsatisfiable,
+                Ok(_) if len == 0 => Range::Unsatisfiable,
+                Ok(n) => Range::Partial(len.saturating_sub(n), len - 1),
+                Err(_) => Range::Full,
+            };
+        }
+
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return Range::Full,
+        };
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+                _ => return Range::Full,
+            }
+        };
+
+        if start >= len {
+            return Range::Unsatisfiable;
+        }
+        Range::Partial(start, end)
+    }
+}
+
+/// streams `len` bytes of the file, starting at `start`
+fn file_body(file: File, start: u64, len: u64) -> Body {
+    let chunks = stream::try_unfold(
+        (file, start, len),
+        |(mut file, start, remaining)| async move {
+            if remaining == 0 {
+                return Ok(None);
+            }
+            if start > 0 {
+                file.seek(SeekFrom::Start(start)).await?;
+            }
+
+            let mut buf = vec![0; remaining.min(CHUNK_SIZE) as usize];
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the file was truncated while it was sent",
+                ));
+            }
+            buf.truncate(n);
+            Ok(Some((Bytes::from(buf), (file, 0, remaining - n as u64))))
+        },
+    );
+    Body::wrap_stream(chunks)
+}
+
+pub(crate) fn http_date(t: SystemTime) -> String {
+    DateTime::<Utc>::from(t)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// guesses the content type from the file extension
+pub fn content_type(path: &Path) -> HeaderValue {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    let mime = match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    };
+    HeaderValue::from_static(mime)
+}
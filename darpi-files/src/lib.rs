@@ -0,0 +1,7 @@
+mod file;
+mod named_file;
+mod serve_dir;
+
+pub use file::content_type;
+pub use named_file::{Disposition, NamedFile, NamedFileErr};
+pub use serve_dir::{serve_dir, ServeDir};
@@ -0,0 +1,180 @@
+use crate::file::{content_type, Opened};
+use darpi::header::{self, HeaderMap, HeaderValue};
+use darpi::response::{RequestResponder, ResponderError};
+use darpi::{Body, RequestParts, Responder, Response, StatusCode};
+use derive_more::Display;
+use std::io;
+use std::path::Path;
+
+/// NamedFile is a single file sent as the response
+/// conditional and range requests are answered from the headers of the request
+/// ```rust,ignore
+/// #[handler([make_container()])]
+/// async fn report(#[inject] db: Arc<dyn Reports>) -> Result<NamedFile, NamedFileErr> {
+///     let file = NamedFile::open(db.latest_path()).await?;
+///     Ok(file.attachment())
+/// }
+/// ```
+pub struct NamedFile {
+    opened: Opened,
+    content_type: HeaderValue,
+    file_name: Option<String>,
+    disposition: Disposition,
+    request: HeaderMap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Disposition {
+    Inline,
+    Attachment,
+}
+
+impl NamedFile {
+    /// the content type is guessed from the extension and the file name is the last path segment
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, NamedFileErr> {
+        let path = path.as_ref();
+        let opened = Opened::open(path).await?;
+        Ok(Self {
+            opened,
+            content_type: content_type(path),
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            disposition: Disposition::Inline,
+            request: HeaderMap::new(),
+        })
+    }
+
+    /// keeps the headers of the request that make it conditional or partial
+    /// for when it is not responded to by a handler
+    pub fn with_request(mut self, headers: &HeaderMap) -> Self {
+        for name in [
+            header::IF_NONE_MATCH,
+            header::IF_MODIFIED_SINCE,
+            header::RANGE,
+            header::IF_RANGE,
+        ] {
+            if let Some(hv) = headers.get(&name) {
+                self.request.insert(name, hv.clone());
+            }
+        }
+        self
+    }
+
+    pub fn content_type(mut self, value: HeaderValue) -> Self {
+        self.content_type = value;
+        self
+    }
+
+    /// the name the client sees, it can be different from the name on disk
+    pub fn file_name(mut self, name: impl Into<String>) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
+
+    pub fn inline(mut self) -> Self {
+        self.disposition = Disposition::Inline;
+        self
+    }
+
+    /// asks the browser to download the file instead of showing it
+    pub fn attachment(mut self) -> Self {
+        self.disposition = Disposition::Attachment;
+        self
+    }
+
+    fn content_disposition(&self) -> String {
+        let kind = match self.disposition {
+            Disposition::Inline => "inline",
+            Disposition::Attachment => "attachment",
+        };
+
+        let name = match &self.file_name {
+            Some(name) => name,
+            None => return kind.to_string(),
+        };
+
+        let fallback: String = name
+            .chars()
+            .map(|c| match c {
+                ' '..='~' if c != '"' && c != '\\' => c,
+                _ => '_',
+            })
+            .collect();
+
+        if fallback == *name {
+            return format!("{}; filename=\"{}\"", kind, name);
+        }
+        // RFC 5987, clients that understand `filename*` prefer it
+        format!(
+            "{}; filename=\"{}\"; filename*=UTF-8''{}",
+            kind,
+            fallback,
+            ext_value(name)
+        )
+    }
+}
+
+fn ext_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+impl Responder for NamedFile {
+    fn respond(self) -> Response<Body> {
+        let rb = Response::builder()
+            .header(header::CONTENT_TYPE, self.content_type.clone())
+            .header(header::CONTENT_DISPOSITION, self.content_disposition());
+        self.opened.respond(&self.request, rb)
+    }
+}
+
+impl RequestResponder for NamedFile {
+    fn respond_to(self, parts: &RequestParts) -> Response<Body> {
+        self.with_request(&parts.headers).respond()
+    }
+}
+
+#[derive(Display, Debug)]
+pub enum NamedFileErr {
+    #[display(fmt = "could not open file: {}", _0)]
+    Open(io::Error),
+}
+
+impl From<io::Error> for NamedFileErr {
+    fn from(e: io::Error) -> Self {
+        Self::Open(e)
+    }
+}
+
+impl ResponderError for NamedFileErr {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Open(e) => match e.kind() {
+                io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+                io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+        }
+    }
+}
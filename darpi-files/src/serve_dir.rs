@@ -0,0 +1,197 @@
+use crate::file::{content_type, Opened};
+use async_trait::async_trait;
+use darpi::header::{self, HeaderMap};
+use darpi::openapi::{Describe, Operation};
+use darpi::request::percent_decode;
+use darpi::{Args, Body, Handler, Request, Response, StatusCode};
+use darpi_headers::{AcceptEncoding, EncodingType};
+use std::convert::Infallible;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// serves the files of a directory from a `{*path}` route
+/// ```rust,ignore
+/// let app = app!({
+///     address: "127.0.0.1:3000",
+///     handlers: [{
+///         route: "/static/{*path}",
+///         method: GET,
+///         handler: serve_dir("./public")
+///     }]
+/// });
+/// ```
+pub fn serve_dir(root: impl Into<PathBuf>) -> ServeDir {
+    ServeDir::new(root)
+}
+
+/// ServeDir streams files from a directory
+/// paths that would leave the directory are answered with `404 Not Found`
+/// a directory is served by its index file, `index.html` by default
+/// a precompressed `.br` or `.gz` sidecar is sent instead of the file when the client accepts it
+#[derive(Clone, Debug)]
+pub struct ServeDir {
+    root: PathBuf,
+    index: String,
+    precompressed: bool,
+}
+
+impl ServeDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index: "index.html".to_string(),
+            precompressed: true,
+        }
+    }
+
+    pub fn index_file(mut self, index: impl Into<String>) -> Self {
+        self.index = index.into();
+        self
+    }
+
+    pub fn precompressed(mut self, yes: bool) -> Self {
+        self.precompressed = yes;
+        self
+    }
+
+    /// serves `path`, relative to the root directory
+    /// it is meant for handlers that check the request before they serve the file
+    pub async fn serve(&self, path: &str, r: &Request<Body>) -> Response<Body> {
+        let mut full = match self.resolve(path) {
+            Some(full) => full,
+            None => return status(StatusCode::NOT_FOUND),
+        };
+
+        match tokio::fs::metadata(&full).await {
+            Ok(meta) if meta.is_dir() => {
+                // relative links in the index file need the trailing slash
+                if !r.uri().path().ends_with('/') {
+                    let location = match r.uri().query() {
+                        Some(query) => format!("{}/?{}", r.uri().path(), query),
+                        None => format!("{}/", r.uri().path()),
+                    };
+                    return Response::builder()
+                        .status(StatusCode::PERMANENT_REDIRECT)
+                        .header(header::LOCATION, location)
+                        .body(Body::empty())
+                        .expect("this cannot happen");
+                }
+                full.push(&self.index);
+            }
+            Ok(_) => {}
+            Err(e) => return io_status(e),
+        }
+
+        let mut rb = Response::builder().header(header::CONTENT_TYPE, content_type(&full));
+        let opened = if self.precompressed {
+            rb = rb.header(header::VARY, "accept-encoding");
+            match self.open_precompressed(&full, r.headers()).await {
+                Ok((opened, Some(encoding))) => {
+                    rb = rb.header(header::CONTENT_ENCODING, encoding);
+                    Ok(opened)
+                }
+                Ok((opened, None)) => Ok(opened),
+                Err(e) => Err(e),
+            }
+        } else {
+            Opened::open(&full).await
+        };
+
+        match opened {
+            Ok(opened) => opened.respond(r.headers(), rb),
+            Err(e) => io_status(e),
+        }
+    }
+
+    /// maps the route argument to a path inside the root directory
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let decoded = percent_decode(path);
+        let mut full = self.root.clone();
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                _ if segment.contains(|c| c == '\\' || c == '\0') => return None,
+                _ if cfg!(windows) && segment.contains(':') => return None,
+                _ => full.push(segment),
+            }
+        }
+        Some(full)
+    }
+
+    /// picks the sidecar with the best quality the client accepts, brotli wins ties
+    async fn open_precompressed(
+        &self,
+        full: &Path,
+        headers: &HeaderMap,
+    ) -> io::Result<(Opened, Option<&'static str>)> {
+        let original = Opened::open(full).await?;
+        let accept = match headers
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|hv| hv.to_str().ok())
+        {
+            Some(accept) => accept,
+            None => return Ok((original, None)),
+        };
+
+        let mut candidates = vec![];
+        for (encoding, ext) in [(EncodingType::Br, "br"), (EncodingType::Gzip, "gz")] {
+            let accepted = AcceptEncoding::parse(accept, encoding);
+            if accepted.encoding == encoding && accepted.quality > 0.0 {
+                candidates.push((accepted.quality, encoding, ext));
+            }
+        }
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (_, encoding, ext) in candidates {
+            let mut sidecar = full.as_os_str().to_owned();
+            sidecar.push(".");
+            sidecar.push(ext);
+            if let Ok(opened) = Opened::open(Path::new(&sidecar)).await {
+                return Ok((opened, Some(encoding.into())));
+            }
+        }
+        Ok((original, None))
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("this cannot happen")
+}
+
+fn io_status(e: io::Error) -> Response<Body> {
+    match e.kind() {
+        io::ErrorKind::NotFound => status(StatusCode::NOT_FOUND),
+        io::ErrorKind::PermissionDenied => status(StatusCode::FORBIDDEN),
+        _ => {
+            darpi::log::error!("could not serve file: {}", e);
+            status(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[async_trait]
+impl<C> Handler<C, (String,)> for ServeDir
+where
+    C: 'static + Sync + Send,
+{
+    async fn call(self, args: Args<C, (String,)>) -> Result<Response<Body>, Infallible> {
+        let (path,) = &args.route_args;
+        Ok(self.serve(path, &args.request).await)
+    }
+}
+
+impl Describe for ServeDir {
+    fn operation() -> Operation {
+        Operation {
+            response: Some((
+                "application/octet-stream",
+                darpi::serde_json::json!({"type": "string", "format": "binary"}),
+            )),
+            ..Default::default()
+        }
+    }
+}
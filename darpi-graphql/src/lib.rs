@@ -4,7 +4,7 @@ use async_graphql::{ParseRequestError, Result};
 use async_trait::async_trait;
 use darpi::header::HeaderValue;
 use darpi::request::{FromRequestBodyWithContainer, QueryPayloadError};
-use darpi::{body::Bytes, header, hyper, response::ResponderError, Body, Query, StatusCode};
+use darpi::{body::Bytes, header, hyper, Body, Query, ResponderError, StatusCode};
 use derive_more::Display;
 use futures_util::{StreamExt, TryStreamExt};
 use http::HeaderMap;
@@ -68,10 +68,14 @@ impl darpi::response::ErrResponder<darpi::request::QueryPayloadError, darpi::Bod
     }
 }
 
-#[derive(Display)]
+#[derive(Display, ResponderError)]
 pub enum GraphQLError {
+    #[status(400)]
     ParseRequest(ParseRequestError),
+    #[status(400)]
     Hyper(hyper::Error),
+    #[status(500)]
+    #[message("could not execute the request")]
     Send(String),
 }
 
@@ -87,8 +91,6 @@ impl From<hyper::Error> for GraphQLError {
     }
 }
 
-impl ResponderError for GraphQLError {}
-
 impl<'de, T> Deserialize<'de> for GraphQLBody<T>
 where
     T: DeserializeOwned,
@@ -43,7 +43,7 @@ impl AcceptEncoding {
         };
         let quality = match parts.len() {
             1 => encoding.quality(),
-            _ => match f64::from_str(parts[1]) {
+            _ => match f64::from_str(parts[1].trim_start_matches("q=")) {
                 Ok(q) => q,
                 Err(_) => 0.0,
             },
@@ -74,3 +74,29 @@ impl AcceptEncoding {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_q_values() {
+        let raw = "gzip;q=0.5, br;q=0.9";
+        assert_eq!(0.5, AcceptEncoding::parse(raw, EncodingType::Gzip).quality);
+        assert_eq!(0.9, AcceptEncoding::parse(raw, EncodingType::Br).quality);
+
+        let mut matched = vec![
+            AcceptEncoding::parse(raw, EncodingType::Gzip),
+            AcceptEncoding::parse(raw, EncodingType::Br),
+        ];
+        matched.sort();
+        assert_eq!(EncodingType::Br, matched[0].encoding);
+    }
+
+    #[test]
+    fn defaults_without_q_value() {
+        let enc = AcceptEncoding::parse("gzip", EncodingType::Gzip);
+        assert_eq!(EncodingType::Gzip, enc.encoding);
+        assert_eq!(EncodingType::Gzip.quality(), enc.quality);
+    }
+}
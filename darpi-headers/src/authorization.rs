@@ -0,0 +1,111 @@
+use darpi::header::{HeaderMap, AUTHORIZATION};
+use darpi::request::{FromHeader, HeaderError};
+use std::ops;
+
+/// Authorization is the `Authorization` header with the credentials of the scheme `S`
+/// ```rust,ignore
+/// #[handler]
+/// async fn me(#[header] auth: Authorization<Bearer>) -> String {
+///     format!("token {}", auth.token())
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Authorization<S>(pub S);
+
+impl<S> Authorization<S> {
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S> ops::Deref for Authorization<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+/// Scheme decodes the credentials of an authentication scheme
+pub trait Scheme: Sized {
+    /// compared case-insensitively with the scheme of the header
+    const NAME: &'static str;
+    fn decode(credentials: &str) -> Option<Self>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bearer(String);
+
+impl Bearer {
+    pub fn token(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Scheme for Bearer {
+    const NAME: &'static str = "Bearer";
+
+    fn decode(credentials: &str) -> Option<Self> {
+        if credentials.is_empty() || credentials.contains(' ') {
+            return None;
+        }
+        Some(Self(credentials.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Basic {
+    username: String,
+    password: String,
+}
+
+impl Basic {
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl Scheme for Basic {
+    const NAME: &'static str = "Basic";
+
+    fn decode(credentials: &str) -> Option<Self> {
+        let decoded = base64::decode(credentials).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let i = decoded.find(':')?;
+        Some(Self {
+            username: decoded[..i].to_string(),
+            password: decoded[i + 1..].to_string(),
+        })
+    }
+}
+
+impl<S: Scheme> FromHeader for Authorization<S> {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        let name = AUTHORIZATION.as_str();
+        let value = headers
+            .get(AUTHORIZATION)
+            .ok_or(HeaderError::Missing(name))?
+            .to_str()
+            .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+
+        let (scheme, credentials) = match value.find(' ') {
+            Some(i) => (&value[..i], value[i + 1..].trim()),
+            None => (value, ""),
+        };
+        if !scheme.eq_ignore_ascii_case(S::NAME) {
+            return Err(HeaderError::Invalid(
+                name,
+                format!("expected the `{}` scheme", S::NAME),
+            ));
+        }
+
+        // the credentials are never part of the error
+        S::decode(credentials)
+            .map(Authorization)
+            .ok_or_else(|| HeaderError::Invalid(name, "malformed credentials".to_string()))
+    }
+}
@@ -0,0 +1,45 @@
+use darpi::header::{HeaderMap, CONTENT_TYPE};
+use darpi::request::{FromHeader, HeaderError};
+
+/// ContentType is the `Content-Type` header
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentType(String);
+
+impl ContentType {
+    /// the media type without parameters, in lowercase
+    pub fn mime(&self) -> String {
+        self.0
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    }
+
+    /// a parameter like `charset`, the name is case-insensitive
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.0.split(';').skip(1).find_map(|param| {
+            let i = param.find('=')?;
+            if param[..i].trim().eq_ignore_ascii_case(name) {
+                return Some(param[i + 1..].trim().trim_matches('"'));
+            }
+            None
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromHeader for ContentType {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        let name = CONTENT_TYPE.as_str();
+        let value = headers
+            .get(CONTENT_TYPE)
+            .ok_or(HeaderError::Missing(name))?
+            .to_str()
+            .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+        Ok(Self(value.to_string()))
+    }
+}
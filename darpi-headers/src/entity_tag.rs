@@ -0,0 +1,90 @@
+/// EntityTags is a list of entity tags, like the value of `If-Match` or `If-None-Match`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityTags {
+    any: bool,
+    tags: Vec<String>,
+}
+
+impl EntityTags {
+    /// the tags are quoted and the quotes can hold commas, `*` matches any tag
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.trim() == "*" {
+            return Ok(Self {
+                any: true,
+                tags: vec![],
+            });
+        }
+
+        let mut tags = vec![];
+        let mut rest = value;
+        loop {
+            rest = rest.trim_start_matches(|c| c == ' ' || c == '\t' || c == ',');
+            if rest.is_empty() {
+                break;
+            }
+
+            let prefix = if rest.starts_with("W/") { 2 } else { 0 };
+            if !rest[prefix..].starts_with('"') {
+                return Err("entity tags must be quoted".to_string());
+            }
+            let end = match rest[prefix + 1..].find('"') {
+                Some(end) => prefix + end + 2,
+                None => return Err("unterminated entity tag".to_string()),
+            };
+            tags.push(rest[..end].to_string());
+
+            rest = rest[end..].trim_start_matches(|c| c == ' ' || c == '\t');
+            if !rest.is_empty() && !rest.starts_with(',') {
+                return Err("entity tags must be separated by commas".to_string());
+            }
+        }
+        Ok(Self { any: false, tags })
+    }
+
+    /// true for `*`
+    pub fn is_any(&self) -> bool {
+        self.any
+    }
+
+    /// the entity tags with their quotes, empty for `*`
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// the weak comparison, `W/"a"` matches `"a"`
+    pub fn matches_weak(&self, etag: &str) -> bool {
+        let etag = etag.trim_start_matches("W/");
+        self.any || self.tags.iter().any(|t| t.trim_start_matches("W/") == etag)
+    }
+
+    /// the strong comparison, weak tags never match
+    pub fn matches_strong(&self, etag: &str) -> bool {
+        if etag.starts_with("W/") {
+            return false;
+        }
+        self.any || self.tags.iter().any(|t| t == etag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commas_inside_quotes() {
+        let tags = EntityTags::parse("\"a,b\", W/\"c\" ,\"\"").unwrap();
+        assert_eq!(&["\"a,b\"", "W/\"c\"", "\"\""], tags.tags());
+        assert!(tags.matches_strong("\"a,b\""));
+        assert!(!tags.matches_strong("\"a\""));
+        assert!(!tags.matches_strong("W/\"c\""));
+        assert!(tags.matches_weak("\"c\""));
+    }
+
+    #[test]
+    fn rejects_invalid_lists() {
+        assert!(EntityTags::parse("a").is_err());
+        assert!(EntityTags::parse("\"a").is_err());
+        assert!(EntityTags::parse("\"a\" \"b\"").is_err());
+        assert!(EntityTags::parse("*").unwrap().is_any());
+    }
+}
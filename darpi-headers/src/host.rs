@@ -0,0 +1,52 @@
+use darpi::header::{HeaderMap, HOST};
+use darpi::request::{FromHeader, HeaderError};
+
+/// Host is the `Host` header, split into the host name and the optional port
+#[derive(Clone, Debug, PartialEq)]
+pub struct Host {
+    hostname: String,
+    port: Option<u16>,
+}
+
+impl Host {
+    /// ipv6 addresses keep their brackets, `[::1]`
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+impl FromHeader for Host {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        let name = HOST.as_str();
+        let value = headers
+            .get(HOST)
+            .ok_or(HeaderError::Missing(name))?
+            .to_str()
+            .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+
+        // the port separator comes after the closing bracket of an ipv6 address
+        let start = value.rfind(']').unwrap_or(0);
+        let (hostname, port) = match value[start..].rfind(':') {
+            Some(i) => {
+                let i = start + i;
+                let port = value[i + 1..]
+                    .parse::<u16>()
+                    .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+                (&value[..i], Some(port))
+            }
+            None => (value, None),
+        };
+
+        if hostname.is_empty() {
+            return Err(HeaderError::Invalid(name, "empty host".to_string()));
+        }
+        Ok(Self {
+            hostname: hostname.to_string(),
+            port,
+        })
+    }
+}
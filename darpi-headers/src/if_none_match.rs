@@ -0,0 +1,35 @@
+use crate::EntityTags;
+use darpi::header::{HeaderMap, IF_NONE_MATCH};
+use darpi::request::{FromHeader, HeaderError};
+
+/// IfNoneMatch is the `If-None-Match` header
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfNoneMatch {
+    tags: EntityTags,
+}
+
+impl IfNoneMatch {
+    /// the weak comparison, `W/"a"` matches `"a"`
+    pub fn matches(&self, etag: &str) -> bool {
+        self.tags.matches_weak(etag)
+    }
+
+    /// the entity tags with their quotes, empty for `*`
+    pub fn tags(&self) -> &[String] {
+        self.tags.tags()
+    }
+}
+
+impl FromHeader for IfNoneMatch {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        let name = IF_NONE_MATCH.as_str();
+        let value = headers
+            .get(IF_NONE_MATCH)
+            .ok_or(HeaderError::Missing(name))?
+            .to_str()
+            .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+
+        let tags = EntityTags::parse(value).map_err(|e| HeaderError::Invalid(name, e))?;
+        Ok(Self { tags })
+    }
+}
@@ -1,6 +1,18 @@
 mod accept_encoding;
+mod authorization;
+mod content_type;
+mod entity_tag;
+mod host;
+mod if_none_match;
+mod user_agent;
 
 pub use accept_encoding::AcceptEncoding;
+pub use authorization::{Authorization, Basic, Bearer, Scheme};
+pub use content_type::ContentType;
+pub use entity_tag::EntityTags;
+pub use host::Host;
+pub use if_none_match::IfNoneMatch;
+pub use user_agent::UserAgent;
 
 use darpi::header::{HeaderValue, ToStrError};
 use derive_more::Display;
@@ -0,0 +1,24 @@
+use darpi::header::{HeaderMap, USER_AGENT};
+use darpi::request::{FromHeader, HeaderError};
+
+/// UserAgent is the `User-Agent` header
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserAgent(String);
+
+impl UserAgent {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromHeader for UserAgent {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        let name = USER_AGENT.as_str();
+        let value = headers
+            .get(USER_AGENT)
+            .ok_or(HeaderError::Missing(name))?
+            .to_str()
+            .map_err(|e| HeaderError::Invalid(name, e.to_string()))?;
+        Ok(Self(value.to_string()))
+    }
+}
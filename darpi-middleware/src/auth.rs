@@ -2,9 +2,7 @@ use async_trait::async_trait;
 use chrono::{Duration, Utc};
 use darpi::{
     header::{HeaderMap, HeaderValue, AUTHORIZATION},
-    middleware,
-    response::ResponderError,
-    Body, Request,
+    middleware, Body, Request, ResponderError,
 };
 use derive_more::Display;
 pub use jsonwebtoken::*;
@@ -231,20 +229,29 @@ fn jwt_from_header(headers: &HeaderMap<HeaderValue>) -> Result<String, Error> {
     Ok(auth_header.trim_start_matches(BEARER).to_owned())
 }
 
-#[derive(Display, Debug)]
+#[derive(Display, Debug, ResponderError)]
 pub enum Error {
+    #[status(401)]
+    #[header("www-authenticate", "Bearer")]
     #[display(fmt = "wrong credentials")]
     WrongCredentialsError,
+    #[status(401)]
+    #[header("www-authenticate", "Bearer error=\"invalid_token\"")]
     #[display(fmt = "jwt token not valid")]
     JWTTokenError,
+    #[status(500)]
+    #[message("jwt token creation error")]
     #[display(fmt = "jwt token creation error {}", _0)]
     JWTTokenCreationError(jsonwebtoken::errors::Error),
+    #[status(401)]
+    #[header("www-authenticate", "Bearer")]
     #[display(fmt = "no auth header")]
     NoAuthHeaderError,
+    #[status(401)]
+    #[header("www-authenticate", "Bearer error=\"invalid_request\"")]
     #[display(fmt = "invalid auth header")]
     InvalidAuthHeaderError,
+    #[status(403)]
     #[display(fmt = "no permission")]
     NoPermissionError,
 }
-
-impl ResponderError for Error {}
@@ -1,9 +1,9 @@
 use async_compression::futures::bufread::{BrotliDecoder, DeflateDecoder, GzipDecoder};
 use async_compression::futures::write::{BrotliEncoder, DeflateEncoder, GzipEncoder};
 use async_trait::async_trait;
-use darpi::header::{ToStrError, ACCEPT_ENCODING, CONTENT_ENCODING};
+use darpi::header::{ToStrError, ACCEPT_ENCODING, CONTENT_ENCODING, ETAG, VARY};
 use darpi::hyper::http::HeaderValue;
-use darpi::{middleware, response::ResponderError, Body, Request, Response, StatusCode};
+use darpi::{middleware, Body, Request, ResponderError, Response, StatusCode};
 use darpi_headers::{AcceptEncoding, ContentEncoding, EncodingType, Error as ContentEncodingError};
 use derive_more::Display;
 use futures_util::{AsyncReadExt, AsyncWriteExt};
@@ -14,6 +14,8 @@ use std::convert::TryFrom;
 /// the priority of the middleware user will be matched against the client's Accept-Encoding header
 /// the match with the highest client weight will be the chosen compression algorithm
 /// if non is found, it will result in a noop
+/// a strong `ETag` of the response is made specific to the chosen encoding
+/// `304 Not Modified` responses have no body and are only given the encoding specific `ETag`
 /// in this example, we can all requests to all handlers will be compressed with gzip
 ///  if the client supports it
 ///```rust,ignore
@@ -62,6 +64,22 @@ pub async fn compress(
         EncodingType::Identity
     };
 
+    if matched_encoding != EncodingType::Identity && matched_encoding != EncodingType::Auto {
+        if let Some(etag) = r
+            .headers()
+            .get(&ETAG)
+            .and_then(|hv| encoded_etag(hv, matched_encoding))
+        {
+            r.headers_mut().insert(ETAG, etag);
+        }
+        r.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    if r.status() == StatusCode::NOT_MODIFIED {
+        return Ok(());
+    }
+
     let mut b = r.body_mut();
     let mut full_body = darpi::body::to_bytes(&mut b)
         .await
@@ -93,6 +111,17 @@ pub async fn compress(
     Ok(())
 }
 
+/// `"abc"` becomes `"abc-gzip"`, weak tags are equivalent across encodings and stay as they are
+fn encoded_etag(etag: &HeaderValue, encoding: EncodingType) -> Option<HeaderValue> {
+    let etag = etag.to_str().ok()?;
+    if etag.starts_with("W/") {
+        return None;
+    }
+    let tag = etag.strip_prefix('"')?.strip_suffix('"')?;
+    let encoding: &str = encoding.into();
+    HeaderValue::from_str(&format!("\"{}-{}\"", tag, encoding)).ok()
+}
+
 /// this middleware will decompress multiple compressions
 /// it supports [Gzip, Deflate, Br, Identity, Auto] and any other compression
 /// will result in an error response with StatusCode::UNSUPPORTED_MEDIA_TYPE
@@ -165,6 +194,10 @@ impl Encoder for Brotli {
             .write_all(bytes)
             .await
             .map_err(|e| Error::EncodingIOError(e))?;
+        writer
+            .close()
+            .await
+            .map_err(|e| Error::EncodingIOError(e))?;
         Ok(writer.into_inner().into())
     }
 }
@@ -196,6 +229,10 @@ impl Encoder for Deflate {
             .write_all(bytes)
             .await
             .map_err(|e| Error::EncodingIOError(e))?;
+        writer
+            .close()
+            .await
+            .map_err(|e| Error::EncodingIOError(e))?;
         Ok(writer.into_inner().into())
     }
 }
@@ -227,6 +264,10 @@ impl Encoder for Gzip {
             .write_all(bytes)
             .await
             .map_err(|e| Error::EncodingIOError(e))?;
+        writer
+            .close()
+            .await
+            .map_err(|e| Error::EncodingIOError(e))?;
         Ok(writer.into_inner().into())
     }
 }
@@ -254,22 +295,23 @@ pub trait Decoder {
     async fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, Error>;
 }
 
-#[derive(Display, Debug)]
+#[derive(Display, Debug, ResponderError)]
 pub enum Error {
+    #[status(500)]
+    #[message("could not encode the response")]
     #[display(fmt = "encoding error {}", _0)]
     EncodingIOError(std::io::Error),
+    #[status(400)]
     #[display(fmt = "decoding error {}", _0)]
     DecodingIOError(std::io::Error),
+    #[status(400)]
     #[display(fmt = "read body error {}", _0)]
     ReadBody(darpi::hyper::Error),
+    #[status(415)]
     #[display(fmt = "invalid content encoding error {}", _0)]
     InvalidContentEncoding(ContentEncodingError),
+    #[status(400)]
     ToStrError(ToStrError),
+    #[status(415)]
     UnsupportedContentEncoding(EncodingType),
 }
-
-impl ResponderError for Error {
-    fn status_code(&self) -> StatusCode {
-        StatusCode::UNSUPPORTED_MEDIA_TYPE
-    }
-}
@@ -0,0 +1,40 @@
+use darpi::cookie::Key;
+use shaku::{Component, Interface};
+
+/// CookieKeyProvider gives handlers the key of signed and private cookies
+/// ```rust,ignore
+/// #[handler({
+///     container: Container
+/// })]
+/// async fn login(
+///     #[inject] keys: Arc<dyn CookieKeyProvider>,
+///     #[cookies] mut jar: CookieJar,
+/// ) -> impl Responder {
+///     jar.add_private(keys.key(), Cookie::new("session", "alice").http_only(true));
+///     "welcome".cookies(jar)
+/// }
+/// ```
+pub trait CookieKeyProvider: Interface {
+    fn key(&self) -> &Key;
+}
+
+/// the key has to be passed as a component parameter
+/// ```rust,ignore
+/// let container = Container::builder()
+///     .with_component_parameters::<CookieKeyProviderImpl>(CookieKeyProviderImplParameters {
+///         key: Key::from_master(&secret).expect("a 64 byte secret"),
+///     })
+///     .build();
+/// ```
+#[derive(Component)]
+#[shaku(interface = CookieKeyProvider)]
+pub struct CookieKeyProviderImpl {
+    #[shaku(default = unimplemented!())]
+    key: Key,
+}
+
+impl CookieKeyProvider for CookieKeyProviderImpl {
+    fn key(&self) -> &Key {
+        &self.key
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::DateTime;
+use darpi::header::{self, HeaderMap, HeaderValue};
+use darpi::{middleware, Body, HttpBody, Method, Request, ResponderError, Response, StatusCode};
+use darpi_headers::EntityTags;
+use derive_more::Display;
+use ring::digest;
+use std::convert::Infallible;
+use std::fmt::Write;
+
+/// Strength decides the kind of `ETag` computed for a response body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strength {
+    /// `"..."`, byte for byte identical representations
+    Strong,
+    /// `W/"..."`, semantically equivalent representations
+    Weak,
+}
+
+/// Preconditions are the conditional headers of a request
+/// they are captured by `conditional` and evaluated by `etag`
+/// handlers that change state should evaluate them before doing so
+/// ```rust,ignore
+/// #[handler]
+/// async fn update(#[request_parts] rp: &RequestParts, #[body] doc: Json<Doc>) -> Result<(), Error> {
+///     let current = db.current_etag();
+///     if let Some(status) = Preconditions::new(&rp.method, &rp.headers).evaluate(Some(&current), None) {
+///         return Err(Error::Precondition(status));
+///     }
+///     db.save(doc.into_inner())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Preconditions {
+    method: Method,
+    if_match: Option<EntityTags>,
+    if_none_match: Option<EntityTags>,
+    if_modified_since: Option<i64>,
+    if_unmodified_since: Option<i64>,
+}
+
+impl Preconditions {
+    pub fn new(method: &Method, headers: &HeaderMap) -> Self {
+        // an invalid list matches no entity tag
+        let tags = |name| {
+            headers.get(name).map(|hv: &HeaderValue| {
+                hv.to_str()
+                    .ok()
+                    .and_then(|hv| EntityTags::parse(hv).ok())
+                    .unwrap_or_default()
+            })
+        };
+        let date = |name| {
+            headers
+                .get(name)
+                .and_then(|hv: &HeaderValue| hv.to_str().ok())
+                .and_then(http_date)
+        };
+        Self {
+            method: method.clone(),
+            if_match: tags(header::IF_MATCH),
+            if_none_match: tags(header::IF_NONE_MATCH),
+            if_modified_since: date(header::IF_MODIFIED_SINCE),
+            if_unmodified_since: date(header::IF_UNMODIFIED_SINCE),
+        }
+    }
+
+    /// true when the request has none of the conditional headers
+    pub fn is_empty(&self) -> bool {
+        self.if_match.is_none()
+            && self.if_none_match.is_none()
+            && self.if_modified_since.is_none()
+            && self.if_unmodified_since.is_none()
+    }
+
+    /// evaluates the preconditions against the validators of the current representation
+    /// in the order of RFC 7232, `None` means the request should be processed
+    /// otherwise it is `304 Not Modified` or `412 Precondition Failed`
+    pub fn evaluate(&self, etag: Option<&str>, last_modified: Option<&str>) -> Option<StatusCode> {
+        let modified = last_modified.and_then(http_date);
+        let safe = self.method == Method::GET || self.method == Method::HEAD;
+
+        if let Some(if_match) = &self.if_match {
+            if !etag.map_or(false, |etag| if_match.matches_strong(etag)) {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        } else if let (Some(since), Some(modified)) = (self.if_unmodified_since, modified) {
+            if modified > since {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+
+        if let Some(if_none_match) = &self.if_none_match {
+            if etag.map_or(false, |etag| if_none_match.matches_weak(etag)) {
+                return match safe {
+                    true => Some(StatusCode::NOT_MODIFIED),
+                    false => Some(StatusCode::PRECONDITION_FAILED),
+                };
+            }
+        } else if let (true, Some(since), Some(modified)) = (safe, self.if_modified_since, modified)
+        {
+            if modified <= since {
+                return Some(StatusCode::NOT_MODIFIED);
+            }
+        }
+        None
+    }
+}
+
+fn http_date(date: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// this middleware captures the conditional headers of the request for `etag`
+/// ```rust,ignore
+/// app!({
+///     address: "127.0.0.1:3000",
+///     middleware: {
+///         request: [conditional()],
+///         response: [compress(&[Gzip]), etag(Strength::Strong, request(0))]
+///     },
+///     handlers: [...]
+/// })
+/// ```
+#[middleware(Request)]
+pub async fn conditional(#[request] r: &Request<Body>) -> Result<Preconditions, Infallible> {
+    Ok(Preconditions::new(r.method(), r.headers()))
+}
+
+/// this middleware adds an `ETag` to successful responses with a buffered body
+/// an `ETag` set by the handler is kept, streamed bodies are left alone
+/// a strong one is suffixed with the `Content-Encoding` of the response, like `"v1-gzip"`
+/// the preconditions of `GET` and `HEAD` requests are then evaluated
+/// the body is replaced by `304 Not Modified` or the request fails with `412 Precondition Failed`
+/// it should be listed after `compress`, so the `ETag` of every encoding differs
+#[middleware(Response)]
+pub async fn etag(
+    #[handler] strength: Strength,
+    #[handler] preconditions: Preconditions,
+    #[response] r: &mut Response<Body>,
+) -> Result<(), Error> {
+    if !r.status().is_success() {
+        return Ok(());
+    }
+
+    if r.headers().contains_key(header::ETAG) {
+        if let Some(etag) = encoded_etag(r.headers()) {
+            r.headers_mut().insert(header::ETAG, etag);
+        }
+    } else if r.body().size_hint().exact().is_some() {
+        let full_body = darpi::body::to_bytes(r.body_mut())
+            .await
+            .map_err(Error::ReadBody)?;
+        let etag = compute(&full_body, strength);
+        *r.body_mut() = Body::from(full_body);
+        r.headers_mut().insert(
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("this cannot happen"),
+        );
+    }
+
+    let safe = preconditions.method == Method::GET || preconditions.method == Method::HEAD;
+    if !safe || preconditions.is_empty() {
+        return Ok(());
+    }
+
+    let etag = r
+        .headers()
+        .get(header::ETAG)
+        .and_then(|hv| hv.to_str().ok());
+    let last_modified = r
+        .headers()
+        .get(header::LAST_MODIFIED)
+        .and_then(|hv| hv.to_str().ok());
+
+    match preconditions.evaluate(etag, last_modified) {
+        Some(StatusCode::NOT_MODIFIED) => {
+            *r.status_mut() = StatusCode::NOT_MODIFIED;
+            *r.body_mut() = Body::empty();
+            for name in [
+                header::CONTENT_TYPE,
+                header::CONTENT_LENGTH,
+                header::CONTENT_ENCODING,
+            ] {
+                r.headers_mut().remove(name);
+            }
+            Ok(())
+        }
+        Some(_) => Err(Error::PreconditionFailed),
+        None => Ok(()),
+    }
+}
+
+/// the `ETag` of the handler names the unencoded representation
+/// `compress` already suffixes it when it encodes the body, other encodings are suffixed here
+fn encoded_etag(headers: &HeaderMap) -> Option<HeaderValue> {
+    let etag = headers.get(header::ETAG)?.to_str().ok()?;
+    let encoding = headers.get(header::CONTENT_ENCODING)?.to_str().ok()?;
+    let encoding: Vec<&str> = encoding
+        .split(',')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty() && *e != "identity")
+        .collect();
+    // weak tags are equivalent across encodings
+    if encoding.is_empty() || etag.starts_with("W/") {
+        return None;
+    }
+
+    let suffix = format!("-{}", encoding.join("-"));
+    let tag = etag.strip_prefix('"')?.strip_suffix('"')?;
+    if tag.ends_with(&suffix) {
+        return None;
+    }
+    HeaderValue::from_str(&format!("\"{}{}\"", tag, suffix)).ok()
+}
+
+/// the length and the first 16 bytes of the SHA-256 digest of the body
+fn compute(body: &[u8], strength: Strength) -> String {
+    let digest = digest::digest(&digest::SHA256, body);
+    let mut etag = String::new();
+    if strength == Strength::Weak {
+        etag.push_str("W/");
+    }
+    write!(etag, "\"{:x}-", body.len()).expect("this cannot happen");
+    for b in &digest.as_ref()[..16] {
+        write!(etag, "{:02x}", b).expect("this cannot happen");
+    }
+    etag.push('"');
+    etag
+}
+
+#[derive(Display, Debug, ResponderError)]
+pub enum Error {
+    #[status(500)]
+    #[message("could not read the response")]
+    #[display(fmt = "read body error {}", _0)]
+    ReadBody(darpi::hyper::Error),
+    #[status(412)]
+    #[display(fmt = "precondition failed")]
+    PreconditionFailed,
+}
@@ -1,5 +1,7 @@
 pub mod auth;
 pub mod compression;
+pub mod cookie;
+pub mod etag;
 
 use darpi::{
     logger::ReqFormatter, logger::RespFormatter, middleware, request::PayloadError, Body, HttpBody,
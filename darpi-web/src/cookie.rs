@@ -0,0 +1,464 @@
+use crate::response::{RequestResponder, Responder};
+use http::header::{self, HeaderMap, HeaderValue};
+use http::request::Parts;
+use hyper::{Body, Response, StatusCode};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, hmac};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// the length of a base64 encoded HMAC-SHA256 tag
+const SIGNATURE_LEN: usize = 44;
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Cookie is a single cookie, sent by the client or set by the server
+/// the name and value are percent encoded in the `Set-Cookie` header when they contain
+/// characters a cookie cannot hold
+/// ```rust,ignore
+/// let session = Cookie::new("session", "abc")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .secure(true)
+///     .http_only(true)
+///     .same_site(SameSite::Lax);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// non-ASCII characters are percent encoded, like in the request path
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(attribute(path.into()));
+        self
+    }
+
+    /// internationalized domains have to be given in their punycode form
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(attribute(domain.into()));
+        self
+    }
+
+    /// without a max age the cookie is removed when the browser is closed
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// browsers ignore `SameSite=None` on insecure cookies, so it implies `Secure`
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// the same cookie with an empty value that expires immediately
+    /// the path and domain have to match the cookie that is removed
+    fn removal(mut self) -> Self {
+        self.value = String::new();
+        self.max_age = Some(Duration::from_secs(0));
+        self
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", encode(&self.name), encode(&self.value))?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        Ok(())
+    }
+}
+
+/// the `Set-Cookie` value of the cookie
+/// ```rust,ignore
+/// Json::new(user).header(SET_COOKIE, cookie.into())
+/// ```
+impl From<Cookie> for HeaderValue {
+    fn from(cookie: Cookie) -> Self {
+        HeaderValue::from_str(&cookie.to_string()).expect("this cannot happen")
+    }
+}
+
+/// removes the characters that would end the attribute
+/// and percent encodes the non-ASCII ones a header cannot hold
+fn attribute(s: String) -> String {
+    let mut attr = String::with_capacity(s.len());
+    for c in s.chars().filter(|c| *c != ';' && !c.is_control()) {
+        if c.is_ascii() {
+            attr.push(c);
+            continue;
+        }
+        let mut buf = [0; 4];
+        for b in c.encode_utf8(&mut buf).bytes() {
+            attr.push_str(&format!("%{:02X}", b));
+        }
+    }
+    attr
+}
+
+/// percent encodes everything that is not a `cookie-octet` of RFC 6265
+fn encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            0x21 | 0x23..=0x24 | 0x26..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// CookieJar holds the cookies of a request and the changes made to them
+/// it is used by the `#[cookies]` handler attribute
+/// the changes are sent back with `SetCookie::cookies`
+/// ```rust,ignore
+/// #[handler]
+/// async fn visit(#[cookies] mut jar: CookieJar) -> impl Responder {
+///     let visits: u32 = jar.get("visits").and_then(|c| c.value().parse().ok()).unwrap_or(0);
+///     jar.add(Cookie::new("visits", (visits + 1).to_string()));
+///     format!("visit number {}", visits + 1).cookies(jar)
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CookieJar {
+    cookies: HashMap<String, Cookie>,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// parses every `Cookie` header, malformed pairs are skipped
+    /// the first cookie wins when a name is sent more than once
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut jar = Self::new();
+        for hv in headers.get_all(header::COOKIE) {
+            let hv = match hv.to_str() {
+                Ok(hv) => hv,
+                Err(_) => continue,
+            };
+            for pair in hv.split(';') {
+                let (name, value) = match pair.trim().split_once('=') {
+                    Some((name, value)) if !name.trim().is_empty() => (name.trim(), value.trim()),
+                    _ => continue,
+                };
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                let name = crate::request::percent_decode(name);
+                jar.cookies
+                    .entry(name.clone())
+                    .or_insert_with(|| Cookie::new(name, crate::request::percent_decode(value)));
+            }
+        }
+        jar
+    }
+
+    /// the cookie as the client will see it after the response
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        self.cookies.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.values()
+    }
+
+    pub fn add(&mut self, cookie: Cookie) {
+        self.cookies.insert(cookie.name.clone(), cookie.clone());
+        self.delta.push(cookie);
+    }
+
+    /// tells the client to drop the cookie
+    /// the path and domain have to match the ones the cookie was set with
+    pub fn remove(&mut self, cookie: Cookie) {
+        self.cookies.remove(&cookie.name);
+        self.delta.push(cookie.removal());
+    }
+
+    /// the cookies added or removed since the jar was parsed
+    pub fn delta(&self) -> impl Iterator<Item = &Cookie> {
+        self.delta.iter()
+    }
+
+    /// adds a cookie the client can read but cannot change
+    pub fn add_signed(&mut self, key: &Key, mut cookie: Cookie) {
+        cookie.value = key.sign(&cookie.name, &cookie.value);
+        self.add(cookie);
+    }
+
+    /// the cookie with its signature removed, `None` if it is missing or was tampered with
+    pub fn get_signed(&self, key: &Key, name: &str) -> Option<Cookie> {
+        let cookie = self.get(name)?;
+        let value = key.verify(name, &cookie.value)?;
+        Some(Cookie {
+            value,
+            ..cookie.clone()
+        })
+    }
+
+    /// adds a cookie the client can neither read nor change
+    pub fn add_private(&mut self, key: &Key, mut cookie: Cookie) {
+        cookie.value = key.encrypt(&cookie.name, &cookie.value);
+        self.add(cookie);
+    }
+
+    /// the decrypted cookie, `None` if it is missing or was tampered with
+    pub fn get_private(&self, key: &Key, name: &str) -> Option<Cookie> {
+        let cookie = self.get(name)?;
+        let value = key.decrypt(name, &cookie.value)?;
+        Some(Cookie {
+            value,
+            ..cookie.clone()
+        })
+    }
+}
+
+/// Key signs and encrypts cookies
+/// signed cookies use HMAC-SHA256 and private cookies use AES-256-GCM
+/// the cookie name is part of both, so a value cannot be moved to another cookie
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl Key {
+    /// the first 32 bytes of the master key sign and the next 32 encrypt
+    /// `None` if the master key is shorter than 64 bytes
+    pub fn from_master(master: &[u8]) -> Option<Self> {
+        if master.len() < 64 {
+            return None;
+        }
+        let mut signing = [0; 32];
+        let mut encryption = [0; 32];
+        signing.copy_from_slice(&master[..32]);
+        encryption.copy_from_slice(&master[32..64]);
+        Some(Self {
+            signing,
+            encryption,
+        })
+    }
+
+    /// a random key, cookies made with it do not survive a restart
+    pub fn generate() -> Self {
+        let mut master = [0; 64];
+        SystemRandom::new()
+            .fill(&mut master)
+            .expect("could not generate a cookie key");
+        Self::from_master(&master).expect("this cannot happen")
+    }
+
+    fn sign(&self, name: &str, value: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.signing);
+        let tag = hmac::sign(&key, format!("{}={}", name, value).as_bytes());
+        let mut signed = base64::encode(tag.as_ref());
+        signed.push_str(value);
+        signed
+    }
+
+    fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        if signed.len() < SIGNATURE_LEN || !signed.is_char_boundary(SIGNATURE_LEN) {
+            return None;
+        }
+        let (tag, value) = signed.split_at(SIGNATURE_LEN);
+        let tag = base64::decode(tag).ok()?;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.signing);
+        hmac::verify(&key, format!("{}={}", name, value).as_bytes(), &tag).ok()?;
+        Some(value.to_string())
+    }
+
+    fn aead_key(&self) -> aead::LessSafeKey {
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &self.encryption)
+            .expect("this cannot happen");
+        aead::LessSafeKey::new(key)
+    }
+
+    fn encrypt(&self, name: &str, value: &str) -> String {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .expect("could not generate a nonce");
+
+        let mut sealed = value.as_bytes().to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(name.as_bytes()),
+                &mut sealed,
+            )
+            .expect("this cannot happen");
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&sealed);
+        base64::encode(data)
+    }
+
+    fn decrypt(&self, name: &str, encrypted: &str) -> Option<String> {
+        let data = base64::decode(encrypted).ok()?;
+        if data.len() < NONCE_LEN + aead::AES_256_GCM.tag_len() {
+            return None;
+        }
+        let (nonce, sealed) = data.split_at(NONCE_LEN);
+        let nonce = aead::Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut sealed = sealed.to_vec();
+        let value = self
+            .aead_key()
+            .open_in_place(nonce, aead::Aad::from(name.as_bytes()), &mut sealed)
+            .ok()?;
+        String::from_utf8(value.to_vec()).ok()
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key(..)")
+    }
+}
+
+/// WithCookies is a responder that adds `Set-Cookie` headers to another responder
+pub struct WithCookies<R> {
+    inner: R,
+    cookies: Vec<Cookie>,
+}
+
+impl<R> WithCookies<R> {
+    pub fn cookie(mut self, cookie: Cookie) -> Self {
+        self.cookies.push(cookie);
+        self
+    }
+
+    pub fn cookies(mut self, jar: CookieJar) -> Self {
+        self.cookies.extend(jar.delta);
+        self
+    }
+
+    fn set_cookies(cookies: Vec<Cookie>, mut resp: Response<Body>) -> Response<Body> {
+        let headers = resp.headers_mut();
+        for cookie in cookies {
+            headers.append(header::SET_COOKIE, cookie.into());
+        }
+        resp
+    }
+}
+
+impl<R: Responder> Responder for WithCookies<R> {
+    fn status_code(&self) -> StatusCode {
+        self.inner.status_code()
+    }
+
+    fn respond(self) -> Response<Body> {
+        Self::set_cookies(self.cookies, self.inner.respond())
+    }
+}
+
+impl<R: RequestResponder> RequestResponder for WithCookies<R> {
+    fn respond_to(self, parts: &Parts) -> Response<Body> {
+        Self::set_cookies(self.cookies, self.inner.respond_to(parts))
+    }
+}
+
+/// SetCookie attaches cookies to any responder
+/// the result is a `Responder` or a `RequestResponder` like the one it wraps
+/// ```rust,ignore
+/// #[handler]
+/// async fn login(#[body] user: Json<User>) -> impl Responder {
+///     let session = Cookie::new("session", user.id.to_string()).http_only(true);
+///     Json::new(user.id).cookie(session)
+/// }
+/// ```
+pub trait SetCookie: Sized {
+    fn cookie(self, cookie: Cookie) -> WithCookies<Self> {
+        WithCookies {
+            inner: self,
+            cookies: vec![cookie],
+        }
+    }
+
+    /// sends back the cookies that were added or removed from the jar
+    fn cookies(self, jar: CookieJar) -> WithCookies<Self> {
+        WithCookies {
+            inner: self,
+            cookies: jar.delta,
+        }
+    }
+}
+
+impl<R> SetCookie for R {}
@@ -0,0 +1,198 @@
+use crate::request::FromRequestBody;
+use crate::response::{Responder, ResponderError};
+use crate::Response;
+use async_trait::async_trait;
+use derive_more::Display;
+use http::header::HeaderName;
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{fmt, ops};
+
+/// the default maximum body size of a `Form`, 16 KiB
+pub const DEFAULT_FORM_LIMIT: u64 = 16 * 1024;
+
+/// Form is an `application/x-www-form-urlencoded` body
+/// the body is rejected once it is larger than `LIMIT` bytes
+/// ```rust,ignore
+/// #[handler]
+/// async fn login(#[body] login: Form<Login, 1024>) -> String {
+///     format!("hello {}", login.name)
+/// }
+/// ```
+pub struct Form<T, const LIMIT: u64 = DEFAULT_FORM_LIMIT> {
+    t: T,
+    hm: HeaderMap,
+}
+
+impl<T, const LIMIT: u64> Form<T, LIMIT> {
+    pub fn new(t: T) -> Self {
+        Self {
+            t,
+            hm: Default::default(),
+        }
+    }
+
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.hm.append(key, value);
+        self
+    }
+
+    pub fn into_inner(self) -> T {
+        self.t
+    }
+
+    async fn deserialize_future(mut b: Body) -> Result<Form<T, LIMIT>, FormErr>
+    where
+        T: DeserializeOwned,
+    {
+        if let Some(upper) = b.size_hint().upper() {
+            if upper > LIMIT {
+                return Err(FormErr::Size(LIMIT, upper));
+            }
+        }
+
+        let mut full_body = Vec::new();
+        while let Some(chunk) = b.data().await {
+            let chunk = chunk?;
+            let len = (full_body.len() + chunk.len()) as u64;
+            if len > LIMIT {
+                return Err(FormErr::Size(LIMIT, len));
+            }
+            full_body.extend_from_slice(&chunk);
+        }
+
+        let ser: T = serde_urlencoded::from_bytes(&full_body)?;
+        Ok(Form::new(ser))
+    }
+}
+
+#[async_trait]
+impl<T, const LIMIT: u64> FromRequestBody<Form<T, LIMIT>, FormErr> for Form<T, LIMIT>
+where
+    T: DeserializeOwned + 'static,
+{
+    async fn assert_content_type(content_type: Option<&HeaderValue>) -> Result<(), FormErr> {
+        if let Some(hv) = content_type {
+            // the content type may carry a charset
+            let mime = hv.to_str().unwrap_or_default().split(';').next();
+            if mime.map(|m| m.trim()) != Some("application/x-www-form-urlencoded") {
+                return Err(FormErr::InvalidContentType);
+            }
+            return Ok(());
+        }
+        Err(FormErr::MissingContentType)
+    }
+    async fn extract(_: &HeaderMap, b: Body) -> Result<Form<T, LIMIT>, FormErr> {
+        Self::deserialize_future(b).await
+    }
+}
+
+impl<'de, T, const LIMIT: u64> Deserialize<'de> for Form<T, LIMIT>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let deser = T::deserialize(deserializer)?;
+        Ok(Form::new(deser))
+    }
+}
+
+impl<T, const LIMIT: u64> ops::Deref for Form<T, LIMIT> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.t
+    }
+}
+
+impl<T, const LIMIT: u64> ops::DerefMut for Form<T, LIMIT> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.t
+    }
+}
+
+impl<T, const LIMIT: u64> fmt::Debug for Form<T, LIMIT>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Form: {:?}", self.t)
+    }
+}
+
+impl<T, const LIMIT: u64> fmt::Display for Form<T, LIMIT>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.t, f)
+    }
+}
+
+impl<T, const LIMIT: u64> Responder for Form<T, LIMIT>
+where
+    T: Serialize,
+{
+    fn respond(self) -> Response<Body> {
+        match serde_urlencoded::to_string(&self.t) {
+            Ok(body) => {
+                let mut rb = Response::builder()
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .status(self.status_code());
+
+                for (hk, hv) in self.hm.iter() {
+                    rb = rb.header(hk, hv);
+                }
+                rb.body(Body::from(body)).expect("this cannot happen")
+            }
+            Err(e) => FormErr::Serialize(e).respond_err(),
+        }
+    }
+}
+
+#[derive(Display)]
+pub enum FormErr {
+    #[display(fmt = "could not read body: {}", _0)]
+    ReadBody(hyper::Error),
+    #[display(fmt = "Form deserialize error: {}", _0)]
+    Deserialize(serde_urlencoded::de::Error),
+    #[display(fmt = "Form serialize error: {}", _0)]
+    Serialize(serde_urlencoded::ser::Error),
+    #[display(fmt = "Form maximum {} exceeded: received {} bytes", _0, _1)]
+    Size(u64, u64),
+    #[display(fmt = "expected content type `application/x-www-form-urlencoded`")]
+    InvalidContentType,
+    #[display(fmt = "missing content type `application/x-www-form-urlencoded`")]
+    MissingContentType,
+}
+
+impl From<serde_urlencoded::de::Error> for FormErr {
+    fn from(e: serde_urlencoded::de::Error) -> Self {
+        Self::Deserialize(e)
+    }
+}
+
+impl From<hyper::Error> for FormErr {
+    fn from(e: hyper::Error) -> Self {
+        Self::ReadBody(e)
+    }
+}
+
+impl ResponderError for FormErr {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ReadBody(_) | Self::Deserialize(_) => StatusCode::BAD_REQUEST,
+            Self::Size(_, _) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::InvalidContentType | Self::MissingContentType => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            Self::Serialize(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -1,21 +1,32 @@
 #![forbid(unsafe_code)]
 
 use async_trait::async_trait;
+pub use form::Form;
 pub use hyper::{body::HttpBody, Body, Request, Response, StatusCode};
 use job::Job;
 pub use json::Json;
+pub use negotiate::Negotiated;
 pub use rayon;
 use std::sync::mpsc::SendError;
 use tokio::sync::oneshot;
 use tokio::sync::oneshot::Receiver;
 
+pub mod cookie;
+pub mod form;
 pub mod handler;
 pub mod job;
 pub mod json;
 pub mod logger;
 pub mod middleware;
+pub mod multipart;
+pub mod negotiate;
+pub mod openapi;
+pub mod problem;
 pub mod request;
 pub mod response;
+pub mod sse;
+pub mod stream;
+pub mod tls;
 pub mod ws;
 pub mod xml;
 pub mod yaml;
@@ -25,6 +36,31 @@ pub trait App {
     async fn run(self) -> Result<(), hyper::Error>;
     fn shutdown_signal(&mut self) -> Option<tokio::sync::oneshot::Sender<()>>;
     fn startup_notify(&mut self) -> Option<tokio::sync::oneshot::Receiver<()>>;
+    /// describes every handler registered with `app!`, in definition order
+    fn routes(&self) -> Vec<RouteInfo>;
+}
+
+/// RouteInfo describes a single handler of an `app!`
+/// the middleware names are listed in the order they are executed
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteInfo {
+    pub route: &'static str,
+    pub method: &'static str,
+    pub handler: &'static str,
+    pub name: Option<&'static str>,
+    pub args: Vec<&'static str>,
+    pub request_middleware: Vec<&'static str>,
+    pub response_middleware: Vec<&'static str>,
+}
+
+impl std::fmt::Display for RouteInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} -> {}", self.method, self.route, self.handler)?;
+        if let Some(name) = self.name {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
 }
 
 pub async fn oneshot<T>(job: impl Into<Job<T>>) -> Result<Receiver<T>, SendError<Job<T>>>
@@ -0,0 +1,666 @@
+use crate::request::FromRequestBody;
+use crate::response::ResponderError;
+use async_trait::async_trait;
+use bytes::{Buf, Bytes, BytesMut};
+use derive_more::Display;
+use http::header::{HeaderName, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use hyper::body::HttpBody;
+use hyper::Body;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+/// the default maximum size of a single part, 16 MiB
+pub const DEFAULT_PART_LIMIT: u64 = 16 * 1024 * 1024;
+/// the default maximum size of the whole body, 64 MiB
+pub const DEFAULT_TOTAL_LIMIT: u64 = 64 * 1024 * 1024;
+
+const MAX_HEADERS_SIZE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 16;
+
+/// how many names are tried when the spooled file already exists
+const SPOOL_ATTEMPTS: usize = 8;
+
+enum State {
+    Preamble,
+    Delimiter,
+    Headers,
+    Body,
+    Done,
+}
+
+/// Multipart is a `multipart/form-data` body
+/// the parts are read one at a time, only the current chunk is kept in memory
+/// ```rust,ignore
+/// #[handler]
+/// async fn upload(#[body] mp: Multipart) -> Result<String, MultipartErr> {
+///     let mut mp = mp.part_limit(1024 * 1024);
+///     while let Some(part) = mp.next_part().await? {
+///         if part.file_name().is_some() {
+///             let file = part.spool().await?;
+///             file.persist(format!("/uploads/{}", file.name()))?;
+///         }
+///     }
+///     Ok("uploaded".to_string())
+/// }
+/// ```
+pub struct Multipart {
+    body: Body,
+    delimiter: Vec<u8>,
+    buf: BytesMut,
+    state: State,
+    part_limit: u64,
+    total_limit: u64,
+    total: u64,
+    spool_dir: PathBuf,
+}
+
+impl Multipart {
+    pub fn new(headers: &HeaderMap, body: Body) -> Result<Self, MultipartErr> {
+        let boundary = boundary(headers.get(CONTENT_TYPE))?;
+
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+
+        // the first delimiter is not preceded by a line break
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"\r\n");
+
+        Ok(Self {
+            body,
+            delimiter,
+            buf,
+            state: State::Preamble,
+            part_limit: DEFAULT_PART_LIMIT,
+            total_limit: DEFAULT_TOTAL_LIMIT,
+            total: 0,
+            spool_dir: std::env::temp_dir(),
+        })
+    }
+
+    /// the maximum size of a single part in bytes
+    pub fn part_limit(mut self, limit: u64) -> Self {
+        self.part_limit = limit;
+        self
+    }
+
+    /// the maximum size of the whole body in bytes
+    pub fn total_limit(mut self, limit: u64) -> Self {
+        self.total_limit = limit;
+        self
+    }
+
+    /// the directory files are spooled to, the system temp directory by default
+    pub fn spool_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spool_dir = dir.into();
+        self
+    }
+
+    /// returns the next part, whatever is left of the previous part is skipped
+    pub async fn next_part(&mut self) -> Result<Option<Part<'_>>, MultipartErr> {
+        loop {
+            match self.state {
+                State::Preamble => match find(&self.buf, &self.delimiter) {
+                    Some(i) => {
+                        self.buf.advance(i + self.delimiter.len());
+                        self.state = State::Delimiter;
+                    }
+                    None => {
+                        // keep the tail, it could be the start of the delimiter
+                        let keep = self.delimiter.len() - 1;
+                        if self.buf.len() > keep {
+                            self.buf.advance(self.buf.len() - keep);
+                        }
+                        self.fill_or_incomplete().await?;
+                    }
+                },
+                State::Delimiter => {
+                    if self.buf.len() < 2 {
+                        self.fill_or_incomplete().await?;
+                        continue;
+                    }
+                    if &self.buf[..2] == b"--" {
+                        self.state = State::Done;
+                        continue;
+                    }
+
+                    // transport padding is allowed before the line break
+                    let line = match find(&self.buf, b"\r\n") {
+                        Some(i) => i,
+                        None => {
+                            self.fill_or_incomplete().await?;
+                            continue;
+                        }
+                    };
+                    if self.buf[..line].iter().any(|b| *b != b' ' && *b != b'\t') {
+                        return Err(MultipartErr::Malformed(
+                            "unexpected bytes after the boundary".to_string(),
+                        ));
+                    }
+                    self.buf.advance(line + 2);
+                    self.state = State::Headers;
+                }
+                State::Headers => {
+                    let end = match find(&self.buf, b"\r\n\r\n") {
+                        Some(end) => end + 4,
+                        None if self.buf.starts_with(b"\r\n") => 2,
+                        None => {
+                            if self.buf.len() > MAX_HEADERS_SIZE {
+                                return Err(MultipartErr::Malformed(
+                                    "part headers are too large".to_string(),
+                                ));
+                            }
+                            self.fill_or_incomplete().await?;
+                            continue;
+                        }
+                    };
+
+                    let headers = parse_headers(&self.buf[..end])?;
+                    self.buf.advance(end);
+                    self.state = State::Body;
+                    return Part::new(self, headers).map(Some);
+                }
+                State::Body => while self.read_chunk().await?.is_some() {},
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// reads every part, text fields are kept in memory and files are spooled
+    pub async fn collect(mut self) -> Result<MultipartForm, MultipartErr> {
+        let mut form = MultipartForm::default();
+        while let Some(part) = self.next_part().await? {
+            if part.file_name().is_some() {
+                form.files.push(part.spool().await?);
+                continue;
+            }
+            let name = part.name().to_string();
+            form.fields.push((name, part.text().await?));
+        }
+        Ok(form)
+    }
+
+    /// returns the next chunk of the current part and `None` once the part is over
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>, MultipartErr> {
+        if !matches!(self.state, State::Body) {
+            return Ok(None);
+        }
+
+        loop {
+            if let Some(i) = find(&self.buf, &self.delimiter) {
+                if i > 0 {
+                    return Ok(Some(self.buf.split_to(i).freeze()));
+                }
+                self.buf.advance(self.delimiter.len());
+                self.state = State::Delimiter;
+                return Ok(None);
+            }
+
+            let keep = self.delimiter.len() - 1;
+            if self.buf.len() > keep {
+                let len = self.buf.len() - keep;
+                return Ok(Some(self.buf.split_to(len).freeze()));
+            }
+            self.fill_or_incomplete().await?;
+        }
+    }
+
+    async fn fill_or_incomplete(&mut self) -> Result<(), MultipartErr> {
+        match self.body.data().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                self.total += chunk.len() as u64;
+                if self.total > self.total_limit {
+                    return Err(MultipartErr::TotalSize(self.total_limit));
+                }
+                self.buf.extend_from_slice(&chunk);
+                Ok(())
+            }
+            None => Err(MultipartErr::Incomplete),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestBody<Multipart, MultipartErr> for Multipart {
+    async fn assert_content_type(content_type: Option<&HeaderValue>) -> Result<(), MultipartErr> {
+        if let Some(hv) = content_type {
+            let mime = hv.to_str().unwrap_or_default().split(';').next();
+            if mime.map(|m| m.trim()) != Some("multipart/form-data") {
+                return Err(MultipartErr::InvalidContentType);
+            }
+            return Ok(());
+        }
+        Err(MultipartErr::MissingContentType)
+    }
+    async fn extract(headers: &HeaderMap, b: Body) -> Result<Multipart, MultipartErr> {
+        Multipart::new(headers, b)
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Multipart")
+    }
+}
+
+/// Part is a single field or file of a `Multipart` body
+pub struct Part<'a> {
+    multipart: &'a mut Multipart,
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    headers: HeaderMap,
+    size: u64,
+}
+
+impl<'a> Part<'a> {
+    fn new(multipart: &'a mut Multipart, headers: HeaderMap) -> Result<Self, MultipartErr> {
+        let disposition = headers
+            .get(http::header::CONTENT_DISPOSITION)
+            .and_then(|hv| hv.to_str().ok())
+            .ok_or_else(|| MultipartErr::Malformed("missing content disposition".to_string()))?;
+        let params = parse_params(disposition);
+
+        let name = params
+            .iter()
+            .find(|(k, _)| k == "name")
+            .map(|(_, v)| v.clone())
+            .ok_or_else(|| MultipartErr::Malformed("missing part name".to_string()))?;
+        let file_name = params
+            .iter()
+            .find(|(k, _)| k == "filename")
+            .map(|(_, v)| v.clone());
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|hv| hv.to_str().ok())
+            .map(|s| s.to_string());
+
+        Ok(Self {
+            multipart,
+            name,
+            file_name,
+            content_type,
+            headers,
+            size: 0,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// returns the next chunk of the part and `None` once the part is over
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartErr> {
+        let chunk = self.multipart.read_chunk().await?;
+        if let Some(chunk) = &chunk {
+            self.size += chunk.len() as u64;
+            if self.size > self.multipart.part_limit {
+                return Err(MultipartErr::PartSize(
+                    self.name.clone(),
+                    self.multipart.part_limit,
+                ));
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// reads the whole part into memory
+    pub async fn bytes(mut self) -> Result<Bytes, MultipartErr> {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(buf.freeze())
+    }
+
+    pub async fn text(self) -> Result<String, MultipartErr> {
+        let name = self.name.clone();
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| MultipartErr::InvalidUtf8(name))
+    }
+
+    /// writes the part to a new file in the spool directory
+    /// the file is removed when the `SpooledFile` is dropped, unless it is persisted
+    pub async fn spool(mut self) -> Result<SpooledFile, MultipartErr> {
+        let (path, mut file) = create_spool_file(&self.multipart.spool_dir, spool_name).await?;
+
+        let mut spooled = SpooledFile {
+            path: Some(path.clone()),
+            name: self.name.clone(),
+            file_name: self.file_name.clone(),
+            content_type: self.content_type.clone(),
+            size: 0,
+        };
+
+        while let Some(chunk) = self.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        spooled.size = self.size;
+        Ok(spooled)
+    }
+}
+
+/// SpooledFile is a file part written to the spool directory
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: Option<PathBuf>,
+    name: String,
+    file_name: Option<String>,
+    content_type: Option<String>,
+    size: u64,
+}
+
+impl SpooledFile {
+    pub fn path(&self) -> &Path {
+        self.path.as_deref().expect("the file is not persisted yet")
+    }
+
+    /// the name of the form field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the file name sent by the client, it should not be trusted as a path
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// moves the file out of the spool directory so it is kept after the drop
+    pub fn persist(mut self, to: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let from = self.path.take().expect("the file is not persisted yet");
+        let to = to.as_ref().to_path_buf();
+        if let Err(e) = std::fs::rename(&from, &to) {
+            self.path = Some(from);
+            return Err(e);
+        }
+        Ok(to)
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            // the file is removed off the runtime threads when there is a runtime
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn_blocking(move || {
+                        let _ = std::fs::remove_file(path);
+                    });
+                }
+                Err(_) => {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// MultipartForm is a fully read `Multipart` body
+#[derive(Debug, Default)]
+pub struct MultipartForm {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<SpooledFile>,
+}
+
+impl MultipartForm {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn file(&self, name: &str) -> Option<&SpooledFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// maps the text fields into a struct that derives `MultipartFields`
+    pub fn parse<T: FromFields>(&self) -> Result<T, MultipartErr> {
+        T::from_fields(&self.fields)
+    }
+}
+
+/// FromFields is implemented by `#[derive(MultipartFields)]`
+/// every field is parsed with `FromStr`, `Option` fields can be missing
+pub trait FromFields: Sized {
+    fn from_fields(fields: &[(String, String)]) -> Result<Self, MultipartErr>;
+}
+
+pub fn field<T>(fields: &[(String, String)], name: &str) -> Result<T, MultipartErr>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    optional_field(fields, name)?.ok_or_else(|| MultipartErr::MissingField(name.to_string()))
+}
+
+pub fn optional_field<T>(fields: &[(String, String)], name: &str) -> Result<Option<T>, MultipartErr>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match fields.iter().find(|(n, _)| n == name) {
+        Some((_, value)) => T::from_str(value)
+            .map(Some)
+            .map_err(|e| MultipartErr::InvalidField(name.to_string(), e.to_string())),
+        None => Ok(None),
+    }
+}
+
+fn boundary(content_type: Option<&HeaderValue>) -> Result<String, MultipartErr> {
+    let content_type = content_type
+        .and_then(|hv| hv.to_str().ok())
+        .ok_or(MultipartErr::MissingContentType)?;
+
+    parse_params(content_type)
+        .into_iter()
+        .find(|(k, _)| k == "boundary")
+        .map(|(_, v)| v)
+        .filter(|v| !v.is_empty() && v.len() <= 70)
+        .ok_or(MultipartErr::MissingBoundary)
+}
+
+/// parses the `key=value` parameters of a header, quoted values may contain `;`
+fn parse_params(header: &str) -> Vec<(String, String)> {
+    let mut params = vec![];
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in header.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    for part in parts.iter().skip(1) {
+        if let Some(i) = part.find('=') {
+            let key = part[..i].trim().to_ascii_lowercase();
+            params.push((key, part[i + 1..].trim().to_string()));
+        }
+    }
+    params
+}
+
+fn parse_headers(buf: &[u8]) -> Result<HeaderMap, MultipartErr> {
+    let mut raw = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let raw = match httparse::parse_headers(buf, &mut raw) {
+        Ok(httparse::Status::Complete((_, raw))) => raw,
+        _ => return Err(MultipartErr::Malformed("invalid part headers".to_string())),
+    };
+
+    let mut headers = HeaderMap::new();
+    for h in raw {
+        let name = HeaderName::from_bytes(h.name.as_bytes())
+            .map_err(|_| MultipartErr::Malformed("invalid part header name".to_string()))?;
+        let value = HeaderValue::from_bytes(h.value)
+            .map_err(|_| MultipartErr::Malformed("invalid part header value".to_string()))?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// a random name, so the spooled files can not be guessed
+fn spool_name() -> io::Result<String> {
+    let mut random = [0_u8; 16];
+    SystemRandom::new()
+        .fill(&mut random)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "could not generate a spool name"))?;
+    let name: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("darpi-{}", name))
+}
+
+/// creates a new file in the directory, an existing file or link at the path is never opened
+async fn create_spool_file(
+    dir: &Path,
+    mut name: impl FnMut() -> io::Result<String>,
+) -> io::Result<(PathBuf, File)> {
+    let mut attempts = 1;
+    loop {
+        let path = dir.join(name()?);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < SPOOL_ATTEMPTS => {
+                attempts += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::find(haystack, needle)
+}
+
+#[derive(Display)]
+pub enum MultipartErr {
+    #[display(fmt = "could not read body: {}", _0)]
+    ReadBody(hyper::Error),
+    #[display(fmt = "could not spool file: {}", _0)]
+    Io(io::Error),
+    #[display(fmt = "invalid multipart body: {}", _0)]
+    Malformed(String),
+    #[display(fmt = "incomplete multipart body")]
+    Incomplete,
+    #[display(fmt = "part `{}` exceeds the maximum of {} bytes", _0, _1)]
+    PartSize(String, u64),
+    #[display(fmt = "multipart body exceeds the maximum of {} bytes", _0)]
+    TotalSize(u64),
+    #[display(fmt = "part `{}` is not valid utf-8", _0)]
+    InvalidUtf8(String),
+    #[display(fmt = "missing field `{}`", _0)]
+    MissingField(String),
+    #[display(fmt = "invalid field `{}`: {}", _0, _1)]
+    InvalidField(String, String),
+    #[display(fmt = "missing multipart boundary")]
+    MissingBoundary,
+    #[display(fmt = "expected content type `multipart/form-data`")]
+    InvalidContentType,
+    #[display(fmt = "missing content type `multipart/form-data`")]
+    MissingContentType,
+}
+
+impl fmt::Debug for MultipartErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<hyper::Error> for MultipartErr {
+    fn from(e: hyper::Error) -> Self {
+        Self::ReadBody(e)
+    }
+}
+
+impl From<io::Error> for MultipartErr {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl ResponderError for MultipartErr {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::PartSize(_, _) | Self::TotalSize(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::InvalidContentType | Self::MissingContentType => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl std::error::Error for MultipartErr {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spooling_does_not_overwrite() {
+        let dir = std::env::temp_dir().join(spool_name().unwrap());
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("taken"), "keep").unwrap();
+
+        let mut names = vec!["free", "taken"];
+        let (path, mut file) = create_spool_file(&dir, || Ok(names.pop().unwrap().to_string()))
+            .await
+            .unwrap();
+        file.write_all(b"spooled").await.unwrap();
+        file.flush().await.unwrap();
+
+        assert_eq!(dir.join("free"), path);
+        assert_eq!("keep", std::fs::read_to_string(dir.join("taken")).unwrap());
+        assert_eq!("spooled", std::fs::read_to_string(&path).unwrap());
+
+        let taken = || Ok("taken".to_string());
+        let err = create_spool_file(&dir, taken).await.unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.kind());
+        assert_eq!("keep", std::fs::read_to_string(dir.join("taken")).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
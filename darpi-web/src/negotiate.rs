@@ -0,0 +1,301 @@
+use crate::request::FromRequestBody;
+use crate::response::{RequestResponder, ResponderError};
+use crate::Response;
+use async_trait::async_trait;
+use bytes::Buf;
+use derive_more::Display;
+use http::header::HeaderName;
+use http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode};
+use hyper::Body;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::{fmt, ops};
+
+/// Format is one of the formats a `Negotiated` value is read from or written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Xml,
+    Yaml,
+}
+
+impl Format {
+    /// the formats in the order of preference when the client accepts several equally
+    const ALL: [Format; 3] = [Format::Json, Format::Xml, Format::Yaml];
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Xml => "application/xml",
+            Format::Yaml => "application/yaml",
+        }
+    }
+
+    fn mimes(&self) -> &'static [&'static str] {
+        match self {
+            Format::Json => &["application/json"],
+            Format::Xml => &["application/xml", "text/xml"],
+            Format::Yaml => &["application/yaml", "application/x-yaml", "text/yaml"],
+        }
+    }
+
+    /// the format of a `Content-Type`, the parameters are ignored
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|f| f.mimes().contains(&mime.as_str()))
+    }
+
+    /// picks the format the client prefers according to the q-values of the `Accept` header
+    /// the most specific media range decides the quality of a format
+    /// JSON is used when the header is missing, `None` means nothing is acceptable
+    pub fn negotiate(accept: Option<&HeaderValue>) -> Option<Self> {
+        let accept = match accept.and_then(|hv| hv.to_str().ok()) {
+            Some(accept) if !accept.trim().is_empty() => accept,
+            _ => return Some(Format::Json),
+        };
+
+        let ranges: Vec<(String, f32)> = accept.split(',').filter_map(media_range).collect();
+
+        let mut best: Option<(Format, f32)> = None;
+        for format in Self::ALL.iter().copied() {
+            let quality = match format.quality(&ranges) {
+                Some(q) if q > 0.0 => q,
+                _ => continue,
+            };
+            if best.map_or(true, |(_, q)| quality > q) {
+                best = Some((format, quality));
+            }
+        }
+        best.map(|(format, _)| format)
+    }
+
+    /// the best quality of the media types of the format
+    fn quality(&self, ranges: &[(String, f32)]) -> Option<f32> {
+        self.mimes()
+            .iter()
+            .filter_map(|mime| mime_quality(mime, ranges))
+            .fold(None, |best, q| match best {
+                Some(b) if b >= q => Some(b),
+                _ => Some(q),
+            })
+    }
+}
+
+/// the quality of the most specific range that matches the media type
+fn mime_quality(mime: &str, ranges: &[(String, f32)]) -> Option<f32> {
+    let main = mime.split('/').next().unwrap_or_default();
+    let mut best: Option<(u8, f32)> = None;
+    for (range, q) in ranges {
+        let specificity = if range == mime {
+            2
+        } else if range.strip_suffix("/*") == Some(main) {
+            1
+        } else if range == "*/*" {
+            0
+        } else {
+            continue;
+        };
+        best = match best {
+            Some((s, bq)) if s > specificity || (s == specificity && bq >= *q) => Some((s, bq)),
+            _ => Some((specificity, *q)),
+        };
+    }
+    best.map(|(_, q)| q)
+}
+
+/// parses `type/subtype; q=0.5`, a malformed quality is treated as 1
+fn media_range(range: &str) -> Option<(String, f32)> {
+    let mut parts = range.split(';');
+    let mime = parts.next()?.trim().to_lowercase();
+    if !mime.contains('/') {
+        return None;
+    }
+    let quality = parts
+        .filter_map(|p| p.trim().strip_prefix("q="))
+        .next()
+        .and_then(|q| q.trim().parse::<f32>().ok())
+        .unwrap_or(1.0);
+    Some((mime, quality))
+}
+
+/// Negotiated is written as JSON, XML or YAML, depending on the `Accept` header of the request
+/// it is a `RequestResponder` only, so it is always responded to with the request at hand
+/// as a body it is read in the format of the `Content-Type` header
+/// ```rust,ignore
+/// #[handler]
+/// async fn user() -> Negotiated<User> {
+///     Negotiated::new(User::default())
+/// }
+///
+/// #[handler]
+/// async fn rename(#[body] mut user: Negotiated<User>) -> Negotiated<User> {
+///     user.name = "john".to_string();
+///     user
+/// }
+/// ```
+pub struct Negotiated<T> {
+    t: T,
+    format: Option<Format>,
+    hm: HeaderMap,
+}
+
+impl<T> Negotiated<T> {
+    pub fn new(t: T) -> Self {
+        Self {
+            t,
+            format: None,
+            hm: Default::default(),
+        }
+    }
+
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.hm.append(key, value);
+        self
+    }
+
+    /// the format the body was sent in
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+
+    pub fn into_inner(self) -> T {
+        self.t
+    }
+
+    async fn deserialize_future(format: Format, b: Body) -> Result<Negotiated<T>, NegotiatedErr>
+    where
+        T: DeserializeOwned,
+    {
+        let full_body = hyper::body::to_bytes(b).await?;
+        let t: T = match format {
+            Format::Json => serde_json::from_slice(&full_body)
+                .map_err(|e| NegotiatedErr::Deserialize(e.to_string()))?,
+            Format::Xml => serde_xml_rs::from_reader(full_body.reader())
+                .map_err(|e| NegotiatedErr::Deserialize(e.to_string()))?,
+            Format::Yaml => serde_yaml::from_slice(&full_body)
+                .map_err(|e| NegotiatedErr::Deserialize(e.to_string()))?,
+        };
+        Ok(Negotiated {
+            format: Some(format),
+            ..Negotiated::new(t)
+        })
+    }
+}
+
+#[async_trait]
+impl<T> FromRequestBody<Negotiated<T>, NegotiatedErr> for Negotiated<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    async fn assert_content_type(content_type: Option<&HeaderValue>) -> Result<(), NegotiatedErr> {
+        let content_type = content_type.ok_or(NegotiatedErr::MissingContentType)?;
+        content_type
+            .to_str()
+            .ok()
+            .and_then(Format::from_content_type)
+            .map(|_| ())
+            .ok_or(NegotiatedErr::InvalidContentType)
+    }
+    async fn extract(headers: &HeaderMap, b: Body) -> Result<Negotiated<T>, NegotiatedErr> {
+        let format = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|hv| hv.to_str().ok())
+            .and_then(Format::from_content_type)
+            .ok_or(NegotiatedErr::InvalidContentType)?;
+        Self::deserialize_future(format, b).await
+    }
+}
+
+impl<T> ops::Deref for Negotiated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.t
+    }
+}
+
+impl<T> ops::DerefMut for Negotiated<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.t
+    }
+}
+
+impl<T> fmt::Debug for Negotiated<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Negotiated: {:?}", self.t)
+    }
+}
+
+impl<T> RequestResponder for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn respond_to(self, parts: &Parts) -> Response<Body> {
+        let format = match Format::negotiate(parts.headers.get(header::ACCEPT)) {
+            Some(format) => format,
+            None => return NegotiatedErr::NotAcceptable.respond_err(),
+        };
+
+        let body = match format {
+            Format::Json => serde_json::to_string(&self.t).map_err(|e| e.to_string()),
+            Format::Xml => serde_xml_rs::to_string(&self.t).map_err(|e| e.to_string()),
+            Format::Yaml => serde_yaml::to_string(&self.t).map_err(|e| e.to_string()),
+        };
+
+        match body {
+            Ok(body) => {
+                let mut rb = Response::builder()
+                    .header(header::CONTENT_TYPE, format.content_type())
+                    .header(header::VARY, "accept")
+                    .status(StatusCode::OK);
+
+                for (hk, hv) in self.hm.iter() {
+                    rb = rb.header(hk, hv);
+                }
+                rb.body(Body::from(body)).expect("this cannot happen")
+            }
+            Err(e) => NegotiatedErr::Serialize(e).respond_err(),
+        }
+    }
+}
+
+#[derive(Display, Debug)]
+pub enum NegotiatedErr {
+    #[display(fmt = "could not read body: {}", _0)]
+    ReadBody(hyper::Error),
+    #[display(fmt = "deserialize error: {}", _0)]
+    Deserialize(String),
+    #[display(fmt = "serialize error: {}", _0)]
+    Serialize(String),
+    #[display(fmt = "none of the accepted media types can be produced")]
+    NotAcceptable,
+    #[display(fmt = "expected a JSON, XML or YAML content type")]
+    InvalidContentType,
+    #[display(fmt = "missing content type")]
+    MissingContentType,
+}
+
+impl From<hyper::Error> for NegotiatedErr {
+    fn from(e: hyper::Error) -> Self {
+        Self::ReadBody(e)
+    }
+}
+
+impl ResponderError for NegotiatedErr {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ReadBody(_) | Self::Deserialize(_) => StatusCode::BAD_REQUEST,
+            Self::Serialize(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            Self::InvalidContentType | Self::MissingContentType => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Schema describes a type as an OpenAPI 3 schema object
+/// it can be derived for structs and field-less enums with `#[derive(Schema)]`
+pub trait Schema {
+    fn schema() -> Value;
+
+    /// `false` for types that can be omitted, like `Option<T>`
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_schema {
+    ($($t:ty => $schema:tt),* $(,)?) => {
+        $(
+            impl Schema for $t {
+                fn schema() -> Value {
+                    json!($schema)
+                }
+            }
+        )*
+    };
+}
+
+impl_schema! {
+    String => {"type": "string"},
+    str => {"type": "string"},
+    char => {"type": "string"},
+    bool => {"type": "boolean"},
+    u8 => {"type": "integer", "format": "int32", "minimum": 0},
+    u16 => {"type": "integer", "format": "int32", "minimum": 0},
+    u32 => {"type": "integer", "format": "int64", "minimum": 0},
+    u64 => {"type": "integer", "format": "int64", "minimum": 0},
+    usize => {"type": "integer", "format": "int64", "minimum": 0},
+    i8 => {"type": "integer", "format": "int32"},
+    i16 => {"type": "integer", "format": "int32"},
+    i32 => {"type": "integer", "format": "int32"},
+    i64 => {"type": "integer", "format": "int64"},
+    isize => {"type": "integer", "format": "int64"},
+    f32 => {"type": "number", "format": "float"},
+    f64 => {"type": "number", "format": "double"},
+    Value => {},
+}
+
+/// a streamed body is described as raw bytes
+impl Schema for crate::request::BodyStream {
+    fn schema() -> Value {
+        json!({"type": "string", "format": "binary"})
+    }
+}
+
+impl<T: Schema + ?Sized> Schema for &T {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: Schema> Schema for HashMap<String, T> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+impl<T: Schema> Schema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+/// Operation is what a handler knows about itself
+/// `#[handler({openapi: {..}})]` fills it with the schemas of the handler arguments
+/// every other handler is documented with its route, method and path arguments only
+#[derive(Clone, Debug, Default)]
+pub struct Operation {
+    pub summary: Option<&'static str>,
+    pub path: Option<Value>,
+    pub query: Option<Value>,
+    pub body: Option<(&'static str, Value)>,
+    pub response: Option<(&'static str, Value)>,
+}
+
+/// implemented by `#[handler]` for every handler
+pub trait Describe {
+    fn operation() -> Operation;
+}
+
+/// describes a handler value, used by `app!` for handlers given as expressions
+pub fn describe<H: Describe>(_: &H) -> Operation {
+    H::operation()
+}
+
+/// maps the request and response formats to their content types
+pub fn content_type(format: &str) -> &'static str {
+    match format {
+        "Json" | "Negotiated" => "application/json",
+        "Xml" => "application/xml",
+        "Yaml" => "application/yaml",
+        "Form" => "application/x-www-form-urlencoded",
+        "String" | "str" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Document is an OpenAPI 3 document built from the handlers of an `app!`
+pub struct Document {
+    title: String,
+    version: String,
+    paths: Map<String, Value>,
+}
+
+impl Document {
+    pub fn new(title: &str, version: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            version: version.to_string(),
+            paths: Map::new(),
+        }
+    }
+
+    /// adds a handler, the route is the one given to `app!`
+    /// argument constraints are removed, `/users/{id:u64}` is documented as `/users/{id}`
+    pub fn operation(
+        mut self,
+        route: &str,
+        method: &str,
+        operation_id: &str,
+        operation: Operation,
+    ) -> Self {
+        let mut path = String::with_capacity(route.len());
+        let mut args = vec![];
+        for (i, part) in route.split('/').enumerate() {
+            if i > 0 {
+                path.push('/');
+            }
+            if part.starts_with('{') && part.ends_with('}') {
+                let name = part[1..part.len() - 1]
+                    .trim_start_matches('*')
+                    .split(':')
+                    .next()
+                    .unwrap_or_default();
+                args.push(name.to_string());
+                path.push_str(&format!("{{{}}}", name));
+                continue;
+            }
+            path.push_str(part);
+        }
+
+        let mut parameters = vec![];
+        for name in args {
+            let schema = operation
+                .path
+                .as_ref()
+                .and_then(|p| p["properties"].get(&name))
+                .cloned()
+                .unwrap_or_else(|| json!({"type": "string"}));
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schema,
+            }));
+        }
+
+        if let Some(query) = &operation.query {
+            let required = query["required"].as_array().cloned().unwrap_or_default();
+            if let Some(properties) = query["properties"].as_object() {
+                for (name, schema) in properties {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&Value::String(name.clone())),
+                        "schema": schema,
+                    }));
+                }
+            }
+        }
+
+        let mut op = Map::new();
+        op.insert("operationId".to_string(), json!(operation_id));
+        if let Some(summary) = operation.summary {
+            op.insert("summary".to_string(), json!(summary));
+        }
+        if !parameters.is_empty() {
+            op.insert("parameters".to_string(), Value::Array(parameters));
+        }
+        if let Some((content_type, schema)) = operation.body {
+            op.insert(
+                "requestBody".to_string(),
+                json!({
+                    "required": true,
+                    "content": {content_type: {"schema": schema}},
+                }),
+            );
+        }
+
+        let response = match operation.response {
+            Some((content_type, schema)) => json!({
+                "description": "OK",
+                "content": {content_type: {"schema": schema}},
+            }),
+            None => json!({"description": "OK"}),
+        };
+        op.insert("responses".to_string(), json!({ "200": response }));
+
+        let item = self
+            .paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(item) = item {
+            item.insert(method.to_lowercase(), Value::Object(op));
+        }
+        self
+    }
+
+    pub fn to_value(&self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": self.title,
+                "version": self.version,
+            },
+            "paths": self.paths,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&self.to_value()).unwrap_or_default()
+    }
+}
@@ -0,0 +1,155 @@
+use crate::response::ResponderError;
+use http::header;
+use hyper::{Body, Response, StatusCode};
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Problem is the structured form of an error response, as described by RFC 7807
+/// every `ResponderError` is turned into a `Problem` and rendered by the `ErrorRenderer` of the app
+/// it can also be returned as an error
+/// ```rust,ignore
+/// #[handler]
+/// async fn user(#[path] p: UserId) -> Result<String, Problem> {
+///     Err(Problem::new(StatusCode::NOT_FOUND)
+///         .problem_type("https://example.com/probs/unknown-user")
+///         .detail(format!("user {} does not exist", p.id)))
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub problem_type: Cow<'static, str>,
+    pub title: Cow<'static, str>,
+    pub status: StatusCode,
+    pub detail: String,
+}
+
+impl Problem {
+    /// an `about:blank` problem titled with the reason phrase of the status code
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            problem_type: Cow::Borrowed("about:blank"),
+            title: Cow::Borrowed(status.canonical_reason().unwrap_or("Unknown Error")),
+            status,
+            detail: String::new(),
+        }
+    }
+
+    /// a URI that identifies the kind of problem
+    pub fn problem_type(mut self, problem_type: impl Into<Cow<'static, str>>) -> Self {
+        self.problem_type = problem_type.into();
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<Cow<'static, str>>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// the explanation of this occurrence of the problem
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = detail.into();
+        self
+    }
+
+    /// the `application/problem+json` document, `detail` is left out when it is empty
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut doc = serde_json::json!({
+            "type": self.problem_type,
+            "title": self.title,
+            "status": self.status.as_u16(),
+        });
+        if !self.detail.is_empty() {
+            doc["detail"] = serde_json::Value::String(self.detail.clone());
+        }
+        doc
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.detail.is_empty() {
+            return write!(f, "{}", self.title);
+        }
+        write!(f, "{}", self.detail)
+    }
+}
+
+impl std::error::Error for Problem {}
+
+impl ResponderError for Problem {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn problem(&self) -> Problem {
+        self.clone()
+    }
+}
+
+/// ErrorRenderer turns every error response of the app into a response
+/// it is set with the `error_renderer` key of `app!`, every app keeps its own
+/// ```rust,ignore
+/// let app = app!({
+///     address: "127.0.0.1:3000",
+///     error_renderer: ProblemJson,
+///     handlers: [...]
+/// });
+/// ```
+pub trait ErrorRenderer: Send + Sync + 'static {
+    fn render(&self, problem: &Problem) -> Response<Body>;
+}
+
+impl<F> ErrorRenderer for F
+where
+    F: Fn(&Problem) -> Response<Body> + Send + Sync + 'static,
+{
+    fn render(&self, problem: &Problem) -> Response<Body> {
+        self(problem)
+    }
+}
+
+/// the default renderer, the body is the detail of the problem
+pub struct PlainText;
+
+impl ErrorRenderer for PlainText {
+    fn render(&self, problem: &Problem) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .status(problem.status)
+            .body(Body::from(problem.detail.clone()))
+            .expect("this cannot happen")
+    }
+}
+
+/// renders errors as `application/problem+json`
+pub struct ProblemJson;
+
+impl ErrorRenderer for ProblemJson {
+    fn render(&self, problem: &Problem) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/problem+json")
+            .status(problem.status)
+            .body(Body::from(problem.to_json().to_string()))
+            .expect("this cannot happen")
+    }
+}
+
+tokio::task_local! {
+    static ERROR_RENDERER: Arc<dyn ErrorRenderer>;
+}
+
+/// `app!` serves every request within the renderer of the app
+#[doc(hidden)]
+pub async fn with_error_renderer<F: Future>(renderer: Arc<dyn ErrorRenderer>, f: F) -> F::Output {
+    ERROR_RENDERER.scope(renderer, f).await
+}
+
+/// renders the problem with the renderer of the app serving the request
+/// `PlainText` is used outside of a request, like in a spawned task
+pub fn render_error(problem: &Problem) -> Response<Body> {
+    ERROR_RENDERER
+        .try_with(|renderer| renderer.render(problem))
+        .unwrap_or_else(|_| PlainText.render(problem))
+}
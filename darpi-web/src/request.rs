@@ -1,18 +1,23 @@
 use crate::response;
 use crate::response::ResponderError;
 use async_trait::async_trait;
+use bytes::Bytes;
 use derive_more::{Display, From};
+use futures::Stream;
 use http::{HeaderMap, HeaderValue};
+use hyper::body::HttpBody;
 use hyper::Body;
-use hyper::Response;
+use hyper::{Response, StatusCode};
 use serde::de;
 use serde_urlencoded;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 #[async_trait]
 pub trait FromRequestBodyWithContainer<T, E, C>
 where
-    T: de::DeserializeOwned + 'static,
+    T: 'static,
     E: ResponderError + 'static,
     C: 'static + Sync + Send,
 {
@@ -25,7 +30,7 @@ where
 #[async_trait]
 pub trait FromRequestBody<T, E>
 where
-    T: de::DeserializeOwned + 'static,
+    T: 'static,
     E: ResponderError + 'static,
 {
     async fn assert_content_type(_content_type: Option<&HeaderValue>) -> Result<(), E> {
@@ -38,7 +43,7 @@ where
 impl<F, T, E, C> FromRequestBodyWithContainer<T, E, C> for F
 where
     F: FromRequestBody<T, E> + 'static,
-    T: de::DeserializeOwned + 'static,
+    T: 'static,
     E: ResponderError + 'static,
     C: std::any::Any + Sync + Send,
 {
@@ -68,6 +73,92 @@ impl FromRequestBody<String, StringErr> for String {
     }
 }
 
+/// BodyStream gives a handler the request body as it arrives
+/// nothing is buffered, a chunk is only read from the connection when the stream is polled
+/// ```rust,ignore
+/// #[handler]
+/// async fn upload(#[body] mut body: BodyStream) -> Result<String, BodyStreamErr> {
+///     let mut size = 0;
+///     while let Some(chunk) = body.next().await {
+///         size += chunk?.len();
+///     }
+///     Ok(format!("received {} bytes", size))
+/// }
+/// ```
+pub struct BodyStream {
+    body: Body,
+    limit: Option<u64>,
+    read: u64,
+}
+
+impl BodyStream {
+    pub fn new(body: Body) -> Self {
+        Self {
+            body,
+            limit: None,
+            read: 0,
+        }
+    }
+
+    /// the stream fails once more than `limit` bytes are read
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// the raw body, useful to proxy it into a response or another request
+    pub fn into_inner(self) -> Body {
+        self.body
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Result<Bytes, BodyStreamErr>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = match Pin::new(&mut self.body).poll_data(cx) {
+            Poll::Ready(Some(Ok(chunk))) => chunk,
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(BodyStreamErr::ReadBody(e)))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        self.read += chunk.len() as u64;
+        if let Some(limit) = self.limit {
+            if self.read > limit {
+                return Poll::Ready(Some(Err(BodyStreamErr::Size(limit, self.read))));
+            }
+        }
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+#[async_trait]
+impl FromRequestBody<BodyStream, BodyStreamErr> for BodyStream {
+    async fn extract(_: &HeaderMap, b: Body) -> Result<BodyStream, BodyStreamErr> {
+        Ok(BodyStream::new(b))
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum BodyStreamErr {
+    #[display(fmt = "could not read body: {}", _0)]
+    ReadBody(hyper::Error),
+    #[display(fmt = "Payload maximum {} exceeded: received {} bytes", _0, _1)]
+    Size(u64, u64),
+}
+
+impl ResponderError for BodyStreamErr {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ReadBody(_) => StatusCode::BAD_REQUEST,
+            Self::Size(_, _) => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
+
+impl std::error::Error for BodyStreamErr {}
+
 #[derive(Debug, Display, From)]
 pub enum RequestErr {
     #[display(fmt = "Not found")]
@@ -88,7 +179,16 @@ pub enum PayloadError {
     Size(u64, u64),
 }
 
-impl ResponderError for PayloadError {}
+// `#[derive(ResponderError)]` emits `darpi::` paths
+// and darpi-web is a dependency of `darpi`, so the errors of this crate implement it by hand
+impl ResponderError for PayloadError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Deserialize(_) | Self::NotExist => StatusCode::BAD_REQUEST,
+            Self::Size(_, _) => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
 
 /// A set of errors that can occur during parsing query strings
 #[derive(Debug, Display, From)]
@@ -124,6 +224,45 @@ impl<T> FromQuery<T, QueryPayloadError> for T {
     }
 }
 
+/// FromHeader builds a typed header from the request headers
+/// it is used by the `#[header]` handler attribute
+/// `Option<T>` is `None` when the header is missing, an invalid header is still an error
+pub trait FromHeader: Sized {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError>;
+}
+
+impl<T: FromHeader> FromHeader for Option<T> {
+    fn from_header(headers: &HeaderMap) -> Result<Self, HeaderError> {
+        match T::from_header(headers) {
+            Ok(t) => Ok(Some(t)),
+            Err(HeaderError::Missing(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum HeaderError {
+    #[display(fmt = "missing header `{}`", _0)]
+    Missing(&'static str),
+    #[display(fmt = "invalid header `{}`: {}", _0, _1)]
+    Invalid(&'static str, String),
+}
+
+impl ResponderError for HeaderError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+impl std::error::Error for HeaderError {}
+
+impl<T: FromHeader> response::ErrResponder<HeaderError, Body> for T {
+    #[cold]
+    fn respond_err(e: HeaderError) -> Response<Body> {
+        e.respond_err()
+    }
+}
+
 #[derive(Debug, Display)]
 pub enum PathError {
     #[display(fmt = "Path deserialize error: {}", _0)]
@@ -135,6 +274,117 @@ pub enum PathError {
 impl ResponderError for PathError {}
 impl std::error::Error for PathError {}
 
+/// decodes the `%XX` sequences of a path argument
+/// the argument is returned unchanged if the result is not valid utf-8
+pub fn percent_decode(arg: &str) -> String {
+    if !arg.contains('%') {
+        return arg.to_string();
+    }
+
+    let bytes = arg.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            decoded.push(hex_value(bytes[i + 1]) << 4 | hex_value(bytes[i + 2]));
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| arg.to_string())
+}
+
+fn hex_value(b: u8) -> u8 {
+    match b {
+        b'0'..=b'9' => b - b'0',
+        b'a'..=b'f' => b - b'a' + 10,
+        _ => b - b'A' + 10,
+    }
+}
+
+/// encodes a path argument, every byte outside of the unreserved set is written as `%XX`
+pub fn percent_encode(arg: &str) -> String {
+    let mut encoded = String::with_capacity(arg.len());
+    for b in arg.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// ToPath is implemented by `#[derive(Path)]`
+/// it returns the fields as route arguments, sorted by name
+pub trait ToPath {
+    fn to_path_args(&self) -> Vec<(&'static str, String)>;
+}
+
+/// routes without arguments
+impl ToPath for () {
+    fn to_path_args(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+}
+
+/// RoutePath is implemented by `#[handler]`
+/// `Path` is the type of the `#[path]` argument, `()` for handlers without one
+/// for every handler with a `name`, `app!` generates a `{name}_url` function that takes it
+/// ```rust,ignore
+/// // route: "/users/{id}", handler: get_user, name: "user_detail"
+/// let path = get_user::user_detail_url(&User { id: 1 });
+/// assert_eq!("/users/1", path);
+/// ```
+pub trait RoutePath {
+    type Path: ToPath;
+}
+
+/// builds the path of a route, the arguments are percent encoded
+#[doc(hidden)]
+pub fn route_path<T: ToPath>(route: &str, args: &T) -> String {
+    let args = args.to_path_args();
+    let mut path = String::with_capacity(route.len());
+    let mut rest = route;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        path.push_str(&rest[..start]);
+
+        let arg = &rest[start + 1..end];
+        let catch_all = arg.starts_with('*');
+        let arg = arg.trim_start_matches('*');
+        let arg_name = arg.split(':').next().unwrap_or(arg);
+        let value = match args.iter().find(|(n, _)| *n == arg_name) {
+            Some((_, value)) => value,
+            // `app!` checks the route arguments against the path type
+            None => unreachable!("route `{}` is missing the argument `{}`", route, arg_name),
+        };
+        if catch_all {
+            // `{*path}` keeps its slashes
+            let segments: Vec<String> = value.split('/').map(percent_encode).collect();
+            path.push_str(&segments.join("/"));
+        } else {
+            path.push_str(&percent_encode(value));
+        }
+        rest = &rest[end + 1..];
+    }
+    path.push_str(rest);
+    path
+}
+
 pub fn assert_respond_err<T, E>(e: E) -> Response<Body>
 where
     T: response::ErrResponder<E, Body>,
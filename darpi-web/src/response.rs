@@ -1,11 +1,10 @@
+use crate::problem::Problem;
 use crate::ws;
-use bytes::BytesMut;
-use http::{header, HeaderValue};
+use http::{header, request::Parts, HeaderValue};
 use hyper::header::SEC_WEBSOCKET_KEY;
 use hyper::{Body, Error, HeaderMap, Response, StatusCode};
 use std::convert::Infallible;
-use std::io::Write;
-use std::{fmt, io};
+use std::fmt;
 
 pub trait Responder {
     fn status_code(&self) -> StatusCode {
@@ -134,38 +133,114 @@ impl Responder for Response<Body> {
     }
 }
 
-pub trait ResponderError: fmt::Display {
-    fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+/// RequestResponder is a responder that sees the request it responds to
+/// it can honour headers like `Accept`, `If-None-Match` or `Range` without the handler passing them
+/// `#[handler]` uses it when the returned type implements it and falls back to `Responder`
+/// ```rust,ignore
+/// impl RequestResponder for Greeting {
+///     fn respond_to(self, parts: &RequestParts) -> Response<Body> {
+///         match parts.headers.get("accept-language") {
+///             Some(lang) if lang.as_bytes().starts_with(b"fr") => "bonjour".respond(),
+///             _ => "hello".respond(),
+///         }
+///     }
+/// }
+/// ```
+pub trait RequestResponder {
+    fn respond_to(self, parts: &Parts) -> Response<Body>;
+}
+
+impl<T> RequestResponder for Option<T>
+where
+    T: RequestResponder,
+{
+    fn respond_to(self, parts: &Parts) -> Response<Body> {
+        match self {
+            Some(t) => t.respond_to(parts),
+            None => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap(),
+        }
     }
-    fn respond_err(&self) -> Response<Body> {
-        let mut buf = BytesMut::new();
-        let _ = write!(ByteWriter(&mut buf), "{}", self);
-        let b = buf.freeze();
+}
 
-        Response::builder()
-            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
-            .status(self.status_code())
-            .body(Body::from(b))
-            .expect("this cannot happen")
+impl<T, E> RequestResponder for Result<T, E>
+where
+    E: ResponderError,
+    T: RequestResponder,
+{
+    fn respond_to(self, parts: &Parts) -> Response<Body> {
+        match self {
+            Ok(t) => t.respond_to(parts),
+            Err(e) => e.respond_err(),
+        }
     }
 }
 
-impl ResponderError for tokio::task::JoinError {}
+/// Respond is used by `#[handler]` to prefer `RequestResponder` over `Responder`
+/// the method is looked up on `&mut Respond<T>` first, which only `ViaRequestResponder` implements
+#[doc(hidden)]
+pub struct Respond<T>(pub Option<T>);
 
-struct ByteWriter<'a>(pub &'a mut BytesMut);
+#[doc(hidden)]
+pub trait ViaRequestResponder {
+    fn needs_parts(&mut self) -> bool;
+    fn respond_with(&mut self, parts: &Parts) -> Response<Body>;
+}
 
-impl<'a> Write for ByteWriter<'a> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.extend_from_slice(buf);
-        Ok(buf.len())
+impl<T: RequestResponder> ViaRequestResponder for Respond<T> {
+    #[inline(always)]
+    fn needs_parts(&mut self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn respond_with(&mut self, parts: &Parts) -> Response<Body> {
+        self.0.take().expect("responded twice").respond_to(parts)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaResponder {
+    fn needs_parts(&mut self) -> bool;
+    fn respond_with(&mut self, parts: &Parts) -> Response<Body>;
+}
+
+impl<T: Responder> ViaResponder for &mut Respond<T> {
+    #[inline(always)]
+    fn needs_parts(&mut self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn respond_with(&mut self, _: &Parts) -> Response<Body> {
+        self.0.take().expect("responded twice").respond()
     }
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+pub trait ResponderError: fmt::Display {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+    /// the problem details of the error, the `Display` text is the detail
+    fn problem(&self) -> Problem {
+        Problem::new(self.status_code()).detail(self.to_string())
+    }
+    /// headers sent with the error, like `WWW-Authenticate` for `401 Unauthorized`
+    fn headers(&self) -> HeaderMap {
+        HeaderMap::new()
+    }
+    /// renders the problem with the `ErrorRenderer` of the app
+    fn respond_err(&self) -> Response<Body> {
+        let mut resp = crate::problem::render_error(&self.problem());
+        resp.headers_mut().extend(self.headers());
+        resp
     }
 }
 
+impl ResponderError for tokio::task::JoinError {}
+
 impl ResponderError for Infallible {}
 impl ResponderError for String {}
 impl ResponderError for &str {}
@@ -187,9 +262,8 @@ impl<T> ErrResponder<crate::request::QueryPayloadError, Body> for Option<T> {
             crate::request::QueryPayloadError::NotExist => "missing query params".to_string(),
         };
 
-        Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(msg))
-            .expect("this not to happen!")
+        Problem::new(StatusCode::BAD_REQUEST)
+            .detail(msg)
+            .respond_err()
     }
 }
@@ -0,0 +1,239 @@
+use crate::response::{RequestResponder, Responder};
+use bytes::Bytes;
+use futures::stream::{BoxStream, StreamExt};
+use futures::Future;
+use http::header::{HeaderName, CACHE_CONTROL, CONTENT_TYPE};
+use http::request::Parts;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use hyper::{Body, Response};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{sleep, Instant, Sleep};
+
+/// the request header a reconnecting client uses to resume after the last event it received
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+/// the default interval of the keep-alive comments, 15 seconds
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Event is a single server-sent event
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// multi-line data is sent as multiple `data` fields
+    pub fn data(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn json<T: Serialize>(t: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::data(serde_json::to_string(t)?))
+    }
+
+    /// line breaks are removed, they would end the field
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(single_line(id.into()));
+        self
+    }
+
+    /// line breaks are removed, they would end the field
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(single_line(event.into()));
+        self
+    }
+
+    /// how long the client waits before it reconnects
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn to_bytes(&self) -> Bytes {
+        let mut buf = String::with_capacity(self.data.len() + 16);
+        if let Some(id) = &self.id {
+            let _ = writeln!(buf, "id: {}", id);
+        }
+        if let Some(event) = &self.event {
+            let _ = writeln!(buf, "event: {}", event);
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(buf, "retry: {}", retry.as_millis());
+        }
+        for line in self.data.split('\n') {
+            let _ = writeln!(buf, "data: {}", line.trim_end_matches('\r'));
+        }
+        buf.push('\n');
+        Bytes::from(buf)
+    }
+}
+
+fn single_line(s: String) -> String {
+    s.replace(['\r', '\n'], "")
+}
+
+type Resume = Box<dyn FnOnce(Option<&str>) -> BoxStream<'static, Event> + Send>;
+
+enum Events {
+    Ready(BoxStream<'static, Event>),
+    Resume(Resume),
+}
+
+/// Sse is a `text/event-stream` response
+/// the response ends with the event stream, idle connections get a keep-alive comment
+/// ```rust,ignore
+/// #[handler]
+/// async fn ticks() -> Sse {
+///     // the `Last-Event-ID` of a reconnecting client is read from the request
+///     Sse::resume(|last_event_id| {
+///         let from = last_event_id
+///             .and_then(|id| id.parse().ok())
+///             .map_or(0, |id: u64| id + 1);
+///         futures::stream::iter(from..)
+///             .map(|i| Event::data(format!("tick {}", i)).id(i.to_string()))
+///     })
+///     .keep_alive(Duration::from_secs(5))
+/// }
+/// ```
+pub struct Sse {
+    events: Events,
+    keep_alive: Option<Duration>,
+    hm: HeaderMap,
+}
+
+impl Sse {
+    pub fn new<S>(events: S) -> Self
+    where
+        S: futures::Stream<Item = Event> + Send + 'static,
+    {
+        Self::with_events(Events::Ready(events.boxed()))
+    }
+
+    /// the stream is created once the response is sent
+    /// with the `Last-Event-ID` of the request, if any
+    pub fn resume<F, S>(events: F) -> Self
+    where
+        F: FnOnce(Option<&str>) -> S + Send + 'static,
+        S: futures::Stream<Item = Event> + Send + 'static,
+    {
+        Self::with_events(Events::Resume(Box::new(move |id| events(id).boxed())))
+    }
+
+    fn with_events(events: Events) -> Self {
+        Self {
+            events,
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+            hm: HeaderMap::new(),
+        }
+    }
+
+    /// the `Last-Event-ID` sent by a reconnecting client
+    pub fn last_event_id(headers: &HeaderMap) -> Option<&str> {
+        headers.get(LAST_EVENT_ID)?.to_str().ok()
+    }
+
+    /// a keep-alive comment is sent once no event was sent for `interval`
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    pub fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.hm.append(key, value);
+        self
+    }
+}
+
+impl Sse {
+    fn into_response(self, last_event_id: Option<&str>) -> Response<Body> {
+        let mut rb = Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+        for (hk, hv) in self.hm.iter() {
+            rb = rb.header(hk, hv);
+        }
+
+        let events = match self.events {
+            Events::Ready(events) => events,
+            Events::Resume(resume) => resume(last_event_id),
+        };
+        let stream = EventStream {
+            events,
+            keep_alive: self.keep_alive,
+            sleep: None,
+        };
+        rb.body(Body::wrap_stream(stream))
+            .expect("this cannot happen")
+    }
+}
+
+/// without the request, a resumed stream starts from the beginning
+impl Responder for Sse {
+    fn respond(self) -> Response<Body> {
+        self.into_response(None)
+    }
+}
+
+impl RequestResponder for Sse {
+    fn respond_to(self, parts: &Parts) -> Response<Body> {
+        let last_event_id = Sse::last_event_id(&parts.headers).map(|id| id.to_string());
+        self.into_response(last_event_id.as_deref())
+    }
+}
+
+struct EventStream {
+    events: BoxStream<'static, Event>,
+    keep_alive: Option<Duration>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl futures::Stream for EventStream {
+    type Item = Result<Bytes, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let interval = match self.keep_alive {
+            Some(interval) => interval,
+            None => {
+                return self
+                    .events
+                    .poll_next_unpin(cx)
+                    .map(|e| e.map(|e| Ok(e.to_bytes())))
+            }
+        };
+
+        match self.events.poll_next_unpin(cx) {
+            Poll::Ready(Some(event)) => {
+                if let Some(sleep) = self.sleep.as_mut() {
+                    sleep.as_mut().reset(Instant::now() + interval);
+                }
+                return Poll::Ready(Some(Ok(event.to_bytes())));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        let sleep = self.sleep.get_or_insert_with(|| Box::pin(sleep(interval)));
+        if sleep.as_mut().poll(cx).is_ready() {
+            sleep.as_mut().reset(Instant::now() + interval);
+            return Poll::Ready(Some(Ok(Bytes::from_static(b": keep-alive\n\n"))));
+        }
+        Poll::Pending
+    }
+}
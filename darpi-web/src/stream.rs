@@ -0,0 +1,160 @@
+use crate::response::Responder;
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use http::header::HeaderName;
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use hyper::{Body, Response};
+use serde::Serialize;
+use std::error::Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// Stream is a chunked response body that is produced while it is sent
+/// ```rust,ignore
+/// #[handler]
+/// async fn export() -> Stream {
+///     let (tx, rx) = stream::channel(16);
+///     darpi::spawn(FutureJob::from(async move {
+///         for row in 0..1_000_000 {
+///             if tx.send(Row { id: row }).await.is_err() {
+///                 return; // the client went away
+///             }
+///         }
+///     }))
+///     .unwrap();
+///     Stream::ndjson(rx)
+/// }
+/// ```
+pub struct Stream {
+    body: Body,
+    hm: HeaderMap,
+    status: StatusCode,
+}
+
+impl Stream {
+    /// raw chunks, sent as `application/octet-stream` unless another content type is set
+    pub fn bytes<S, B, E>(s: S) -> Self
+    where
+        S: futures::Stream<Item = Result<B, E>> + Send + 'static,
+        B: Into<Bytes> + 'static,
+        E: Into<Box<dyn Error + Send + Sync>> + 'static,
+    {
+        Self::new(Body::wrap_stream(s), "application/octet-stream")
+    }
+
+    /// one json document per line, sent as `application/x-ndjson`
+    pub fn ndjson<S, T>(s: S) -> Self
+    where
+        S: futures::Stream<Item = T> + Send + 'static,
+        T: Serialize,
+    {
+        let lines = s.map(|item| {
+            serde_json::to_vec(&item).map(|mut line| {
+                line.push(b'\n');
+                Bytes::from(line)
+            })
+        });
+        Self::new(Body::wrap_stream(lines), "application/x-ndjson")
+    }
+
+    /// a single json array, the items are written one at a time
+    pub fn json_array<S, T>(s: S) -> Self
+    where
+        S: futures::Stream<Item = T> + Send + 'static,
+        T: Serialize,
+    {
+        let items = s.enumerate().map(|(i, item)| {
+            serde_json::to_vec(&item).map(|json| {
+                let mut chunk = Vec::with_capacity(json.len() + 1);
+                if i > 0 {
+                    chunk.push(b',');
+                }
+                chunk.extend_from_slice(&json);
+                Bytes::from(chunk)
+            })
+        });
+
+        let array = stream::once(async { Ok(Bytes::from_static(b"[")) })
+            .chain(items)
+            .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }));
+        Self::new(Body::wrap_stream(array), "application/json")
+    }
+
+    fn new(body: Body, content_type: &'static str) -> Self {
+        let mut hm = HeaderMap::new();
+        hm.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        Self {
+            body,
+            hm,
+            status: StatusCode::OK,
+        }
+    }
+
+    pub fn content_type(mut self, value: HeaderValue) -> Self {
+        self.hm.insert(header::CONTENT_TYPE, value);
+        self
+    }
+
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.hm.append(key, value);
+        self
+    }
+
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl Responder for Stream {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn respond(self) -> Response<Body> {
+        let mut rb = Response::builder().status(self.status);
+        for (hk, hv) in self.hm.iter() {
+            rb = rb.header(hk, hv);
+        }
+        rb.body(self.body).expect("this cannot happen")
+    }
+}
+
+/// creates a bounded channel to feed a `Stream`
+/// `Sender::send` waits while `buffer` items are queued, so a producer can not outrun the client
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (Sender(tx), Receiver(rx))
+}
+
+#[derive(Debug)]
+pub struct Sender<T>(mpsc::Sender<T>);
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Sender<T> {
+    /// fails once the response is dropped, usually because the client disconnected
+    pub async fn send(&self, t: T) -> Result<(), mpsc::error::SendError<T>> {
+        self.0.send(t).await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+#[derive(Debug)]
+pub struct Receiver<T>(mpsc::Receiver<T>);
+
+impl<T> futures::Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.0.poll_recv(cx)
+    }
+}
@@ -0,0 +1,336 @@
+use derive_more::Display;
+use hyper::server::accept::Accept;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::sign::{any_supported_type, CertifiedKey};
+pub use rustls::ServerConfig;
+use rustls::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, Certificate, ClientHello,
+    NoClientAuth, PrivateKey, ResolvesServerCert, RootCertStore,
+};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Semaphore};
+pub use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+const ACCEPT_QUEUE_SIZE: usize = 128;
+const MAX_PENDING_HANDSHAKES: usize = 256;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Display)]
+pub enum TlsError {
+    #[display(fmt = "could not read `{}`: {}", "_0.display()", _1)]
+    Io(PathBuf, io::Error),
+    #[display(fmt = "invalid certificate chain in `{}`", "_0.display()")]
+    InvalidCertificate(PathBuf),
+    #[display(fmt = "invalid private key in `{}`", "_0.display()")]
+    InvalidPrivateKey(PathBuf),
+    #[display(fmt = "no private key found in `{}`", "_0.display()")]
+    MissingPrivateKey(PathBuf),
+    #[display(fmt = "invalid client certificate authority in `{}`", "_0.display()")]
+    InvalidClientCa(PathBuf),
+}
+
+impl std::error::Error for TlsError {}
+
+/// TlsConfig describes how the server certificate is loaded from disk
+/// ```rust, ignore
+/// let tls = TlsConfig::new("cert.pem", "key.pem")
+///     // require and verify client certificates signed by this authority
+///     .client_ca("ca.pem")
+///     // check the certificate files for changes every minute
+///     .reload_interval(Duration::from_secs(60))
+///     // drop clients that have not finished the handshake after 5 seconds
+///     .handshake_timeout(Duration::from_secs(5))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    cert: PathBuf,
+    key: PathBuf,
+    client_ca: Option<PathBuf>,
+    client_auth_optional: bool,
+    reload_interval: Option<Duration>,
+    handshake_timeout: Duration,
+}
+
+impl TlsConfig {
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            cert: cert.into(),
+            key: key.into(),
+            client_ca: None,
+            client_auth_optional: false,
+            reload_interval: None,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        }
+    }
+
+    /// enables client certificate verification against the
+    /// certificate authorities found in the given pem file
+    pub fn client_ca(mut self, client_ca: impl Into<PathBuf>) -> Self {
+        self.client_ca = Some(client_ca.into());
+        self
+    }
+
+    /// when set, clients without a certificate are still accepted
+    /// but the ones presenting a certificate are verified
+    pub fn client_auth_optional(mut self, yes: bool) -> Self {
+        self.client_auth_optional = yes;
+        self
+    }
+
+    /// the certificate and the key are reloaded from disk
+    /// when their modification time changes
+    pub fn reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = Some(interval);
+        self
+    }
+
+    /// connections that do not complete the tls handshake
+    /// within this duration are closed, defaults to 10 seconds
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> Result<Tls, TlsError> {
+        let verifier = match &self.client_ca {
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                let (valid, _) = roots
+                    .add_pem_file(&mut open(ca)?)
+                    .map_err(|_| TlsError::InvalidClientCa(ca.clone()))?;
+                if valid == 0 {
+                    return Err(TlsError::InvalidClientCa(ca.clone()));
+                }
+
+                if self.client_auth_optional {
+                    AllowAnyAnonymousOrAuthenticatedClient::new(roots)
+                } else {
+                    AllowAnyAuthenticatedClient::new(roots)
+                }
+            }
+            None => NoClientAuth::new(),
+        };
+
+        let resolver = Arc::new(CertResolver {
+            key: RwLock::new(load_certified_key(&self.cert, &self.key)?),
+            modified: RwLock::new(modified(&self.cert, &self.key)),
+            cert: self.cert,
+            key_path: self.key,
+        });
+
+        let mut config = ServerConfig::new(verifier);
+        config.cert_resolver = resolver.clone();
+
+        let reloader = self
+            .reload_interval
+            .map(|interval| (Arc::downgrade(&resolver), interval));
+
+        Ok(Tls::new(config, reloader).handshake_timeout(self.handshake_timeout))
+    }
+}
+
+/// Tls holds a ready to use rustls server configuration
+pub struct Tls {
+    config: Arc<ServerConfig>,
+    reloader: Option<(Weak<CertResolver>, Duration)>,
+    handshake_timeout: Duration,
+}
+
+impl Tls {
+    fn new(mut config: ServerConfig, reloader: Option<(Weak<CertResolver>, Duration)>) -> Self {
+        if config.alpn_protocols.is_empty() {
+            let protocols: Vec<Vec<u8>> = ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect();
+            config.set_protocols(&protocols);
+        }
+
+        Self {
+            config: Arc::new(config),
+            reloader,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        }
+    }
+
+    /// uses a user provided rustls configuration as is
+    /// the `h2` and `http/1.1` ALPN protocols are set if none are present
+    pub fn from_server_config(config: ServerConfig) -> Self {
+        Self::new(config, None)
+    }
+
+    /// connections that do not complete the tls handshake
+    /// within this duration are closed, defaults to 10 seconds
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// binds the address and returns an incoming stream of tls connections
+    /// that can be passed to `darpi::Server::builder`
+    pub async fn bind(self, address: &SocketAddr) -> io::Result<TlsIncoming> {
+        let listener = TcpListener::bind(address).await?;
+        let acceptor = TlsAcceptor::from(self.config);
+        let (tx, rx) = mpsc::channel(ACCEPT_QUEUE_SIZE);
+
+        if let Some((resolver, interval)) = self.reloader {
+            tokio::spawn(reload(resolver, interval));
+        }
+
+        tokio::spawn(accept(listener, acceptor, self.handshake_timeout, tx));
+        Ok(TlsIncoming { rx })
+    }
+}
+
+pub struct TlsIncoming {
+    rx: mpsc::Receiver<TlsStream<TcpStream>>,
+}
+
+impl Accept for TlsIncoming {
+    type Conn = TlsStream<TcpStream>;
+    type Error = io::Error;
+
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.rx.poll_recv(cx).map(|conn| conn.map(Ok))
+    }
+}
+
+async fn accept(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    handshake_timeout: Duration,
+    tx: mpsc::Sender<TlsStream<TcpStream>>,
+) {
+    // the queue only bounds completed connections
+    // this bounds the ones still in the middle of a handshake
+    let pending = Arc::new(Semaphore::new(MAX_PENDING_HANDSHAKES));
+    loop {
+        let permit = tokio::select! {
+            _ = tx.closed() => return,
+            permit = pending.clone().acquire_owned() => permit.expect("semaphore is never closed"),
+        };
+
+        let stream = tokio::select! {
+            _ = tx.closed() => return,
+            res = listener.accept() => match res {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("could not accept tcp connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let tx = tx.clone();
+        // a slow handshake should not block the rest of the connections
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await;
+            drop(permit);
+            match handshake {
+                Ok(Ok(tls)) => {
+                    let _ = tx.send(tls).await;
+                }
+                Ok(Err(e)) => log::debug!("tls handshake failed: {}", e),
+                Err(_) => log::debug!("tls handshake timed out"),
+            }
+        });
+    }
+}
+
+async fn reload(resolver: Weak<CertResolver>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let resolver = match resolver.upgrade() {
+            Some(r) => r,
+            None => return,
+        };
+        resolver.reload_if_modified();
+    }
+}
+
+struct CertResolver {
+    cert: PathBuf,
+    key_path: PathBuf,
+    key: RwLock<CertifiedKey>,
+    modified: RwLock<Option<(SystemTime, SystemTime)>>,
+}
+
+impl CertResolver {
+    fn reload_if_modified(&self) {
+        let modified = modified(&self.cert, &self.key_path);
+        if modified.is_none() || *self.modified.read().expect("poisoned lock") == modified {
+            return;
+        }
+
+        match load_certified_key(&self.cert, &self.key_path) {
+            Ok(key) => {
+                *self.key.write().expect("poisoned lock") = key;
+                *self.modified.write().expect("poisoned lock") = modified;
+                log::info!("reloaded tls certificate `{}`", self.cert.display());
+            }
+            // the files might be in the middle of being replaced
+            // the previous certificate is kept until the next attempt
+            Err(e) => log::warn!("could not reload tls certificate: {}", e),
+        }
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _: ClientHello) -> Option<CertifiedKey> {
+        Some(self.key.read().expect("poisoned lock").clone())
+    }
+}
+
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert = std::fs::metadata(cert).and_then(|m| m.modified()).ok()?;
+    let key = std::fs::metadata(key).and_then(|m| m.modified()).ok()?;
+    Some((cert, key))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| TlsError::Io(path.to_path_buf(), e))
+}
+
+fn load_certified_key(cert: &Path, key: &Path) -> Result<CertifiedKey, TlsError> {
+    let chain: Vec<Certificate> =
+        certs(&mut open(cert)?).map_err(|_| TlsError::InvalidCertificate(cert.to_path_buf()))?;
+    if chain.is_empty() {
+        return Err(TlsError::InvalidCertificate(cert.to_path_buf()));
+    }
+
+    let private_key = load_private_key(key)?;
+    let signing_key = any_supported_type(&private_key)
+        .map_err(|_| TlsError::InvalidPrivateKey(key.to_path_buf()))?;
+
+    Ok(CertifiedKey::new(chain, Arc::new(signing_key)))
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKey, TlsError> {
+    let mut keys = pkcs8_private_keys(&mut open(path)?)
+        .map_err(|_| TlsError::InvalidPrivateKey(path.to_path_buf()))?;
+
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut open(path)?)
+            .map_err(|_| TlsError::InvalidPrivateKey(path.to_path_buf()))?;
+    }
+
+    if keys.is_empty() {
+        return Err(TlsError::MissingPrivateKey(path.to_path_buf()));
+    }
+    Ok(keys.remove(0))
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+
+/// Constraint restricts the values a route argument accepts
+/// it is written after the argument name, separated by a colon
+/// `{id:u64}`, `{id:uuid}` or `{slug:[a-z0-9-]+}`
+/// a segment that does not satisfy the constraint does not match the route
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Constraint {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    Uuid,
+    Class(CharClass),
+}
+
+/// CharClass is a single character class with an optional quantifier
+/// `[a-z]` matches exactly one character, `[a-z]?` at most one,
+/// `[a-z]+` at least one and `[a-z]*` any number of characters
+/// a leading `^` negates the class
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CharClass {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+    min: usize,
+    max: Option<usize>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConstraintError(String);
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+impl Constraint {
+    pub fn parse(s: &str) -> Result<Self, ConstraintError> {
+        let c = match s {
+            "u8" => Self::U8,
+            "u16" => Self::U16,
+            "u32" => Self::U32,
+            "u64" => Self::U64,
+            "u128" => Self::U128,
+            "usize" => Self::Usize,
+            "i8" => Self::I8,
+            "i16" => Self::I16,
+            "i32" => Self::I32,
+            "i64" => Self::I64,
+            "i128" => Self::I128,
+            "isize" => Self::Isize,
+            "uuid" => Self::Uuid,
+            _ if s.starts_with('[') => Self::Class(CharClass::parse(s)?),
+            _ => {
+                return Err(ConstraintError(format!(
+                    "unknown constraint `{}`. Available constraints are integer types, `uuid` and character classes like `[a-z0-9-]+`",
+                    s
+                )))
+            }
+        };
+        Ok(c)
+    }
+
+    pub fn matches(&self, segment: &[u8]) -> bool {
+        let s = match std::str::from_utf8(segment) {
+            Ok(s) => s,
+            Err(_) => return false,
+        };
+
+        // `FromStr` for integers accepts a leading `+`
+        // only the canonical form is allowed in a path
+        let is_int = !s.starts_with('+');
+
+        match self {
+            Self::U8 => is_int && s.parse::<u8>().is_ok(),
+            Self::U16 => is_int && s.parse::<u16>().is_ok(),
+            Self::U32 => is_int && s.parse::<u32>().is_ok(),
+            Self::U64 => is_int && s.parse::<u64>().is_ok(),
+            Self::U128 => is_int && s.parse::<u128>().is_ok(),
+            Self::Usize => is_int && s.parse::<usize>().is_ok(),
+            Self::I8 => is_int && s.parse::<i8>().is_ok(),
+            Self::I16 => is_int && s.parse::<i16>().is_ok(),
+            Self::I32 => is_int && s.parse::<i32>().is_ok(),
+            Self::I64 => is_int && s.parse::<i64>().is_ok(),
+            Self::I128 => is_int && s.parse::<i128>().is_ok(),
+            Self::Isize => is_int && s.parse::<isize>().is_ok(),
+            Self::Uuid => is_uuid(segment),
+            Self::Class(class) => class.matches(s),
+        }
+    }
+}
+
+impl CharClass {
+    fn parse(s: &str) -> Result<Self, ConstraintError> {
+        let err = |msg: &str| ConstraintError(format!("invalid character class `{}`: {}", s, msg));
+
+        let close = match s.rfind(']') {
+            Some(i) if i > 0 => i,
+            _ => return Err(err("missing `]`")),
+        };
+
+        let (min, max) = match &s[close + 1..] {
+            "" => (1, Some(1)),
+            "?" => (0, Some(1)),
+            "+" => (1, None),
+            "*" => (0, None),
+            _ => return Err(err("only `?`, `+` and `*` are allowed after `]`")),
+        };
+
+        let mut chars: Vec<char> = s[1..close].chars().collect();
+        let negated = chars.first() == Some(&'^');
+        if negated {
+            chars.remove(0);
+        }
+
+        if chars.is_empty() {
+            return Err(err("empty class"));
+        }
+
+        let mut ranges = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '/' || c == '[' || c == ']' || c == '{' || c == '}' {
+                return Err(err(&format!("`{}` is not allowed", c)));
+            }
+
+            // a `-` at the start or at the end of the class is a literal
+            if i + 2 < chars.len() && chars[i + 1] == '-' {
+                let end = chars[i + 2];
+                if end < c {
+                    return Err(err(&format!("invalid range `{}-{}`", c, end)));
+                }
+                ranges.push((c, end));
+                i += 3;
+                continue;
+            }
+
+            ranges.push((c, c));
+            i += 1;
+        }
+
+        Ok(Self {
+            negated,
+            ranges,
+            min,
+            max,
+        })
+    }
+
+    fn matches(&self, s: &str) -> bool {
+        let mut count = 0;
+        for c in s.chars() {
+            let in_class = self
+                .ranges
+                .iter()
+                .any(|(start, end)| *start <= c && c <= *end);
+            if in_class == self.negated {
+                return false;
+            }
+            count += 1;
+        }
+
+        count >= self.min && self.max.map_or(true, |max| count <= max)
+    }
+}
+
+fn is_uuid(segment: &[u8]) -> bool {
+    if segment.len() != 36 {
+        return false;
+    }
+
+    segment.iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int() {
+        let c = Constraint::parse("u8").unwrap();
+        assert!(c.matches(b"255"));
+        assert!(!c.matches(b"256"));
+        assert!(!c.matches(b"-1"));
+        assert!(!c.matches(b"+1"));
+        assert!(!c.matches(b""));
+
+        let c = Constraint::parse("i64").unwrap();
+        assert!(c.matches(b"-1"));
+        assert!(!c.matches(b"me"));
+    }
+
+    #[test]
+    fn test_uuid() {
+        let c = Constraint::parse("uuid").unwrap();
+        assert!(c.matches(b"67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!c.matches(b"67e55044-10b1-426f-9247-bb680e5fe0c"));
+        assert!(!c.matches(b"67e55044x10b1-426f-9247-bb680e5fe0c8"));
+    }
+
+    #[test]
+    fn test_class() {
+        let c = Constraint::parse("[a-z0-9-]+").unwrap();
+        assert!(c.matches(b"hello-world-1"));
+        assert!(!c.matches(b"Hello"));
+        assert!(!c.matches(b""));
+
+        let c = Constraint::parse("[-a]").unwrap();
+        assert!(c.matches(b"-"));
+        assert!(c.matches(b"a"));
+        assert!(!c.matches(b"aa"));
+
+        let c = Constraint::parse("[^.]*").unwrap();
+        assert!(c.matches(b""));
+        assert!(c.matches(b"file"));
+        assert!(!c.matches(b"file.txt"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Constraint::parse("u63").is_err());
+        assert!(Constraint::parse("[a-z").is_err());
+        assert!(Constraint::parse("[]").is_err());
+        assert!(Constraint::parse("[z-a]").is_err());
+        assert!(Constraint::parse("[a/b]").is_err());
+        assert!(Constraint::parse("[a-z]{2}").is_err());
+    }
+}
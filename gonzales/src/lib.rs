@@ -7,12 +7,26 @@ const RESERVED_BYTE: u8 = 17;
 const RESERVED_BYTE_INDEX: usize = RESERVED_BYTE as usize;
 const ASTERISK_BYTE: u8 = 18;
 const ASTERISK_BYTE_INDEX: usize = ASTERISK_BYTE as usize;
+const CATCH_ALL_BYTE: u8 = 19;
+const CATCH_ALL_BYTE_INDEX: usize = CATCH_ALL_BYTE as usize;
+
+mod constraint;
+
+pub use constraint::{CharClass, Constraint, ConstraintError};
 
 pub struct RouterBuilder {
     ascii_case_insensitive: bool,
     has_asterisk: bool,
 }
 
+enum Token {
+    Byte(u8),
+    Arg(Option<Constraint>),
+    Asterisk,
+    // `{*name}`, the rest of the path as a single argument
+    CatchAll,
+}
+
 impl RouterBuilder {
     pub fn new() -> RouterBuilder {
         RouterBuilder {
@@ -20,7 +34,7 @@ impl RouterBuilder {
             has_asterisk: false,
         }
     }
-    fn replace<I, P>(&mut self, patterns: I) -> Vec<Vec<u8>>
+    fn replace<I, P>(&mut self, patterns: I) -> Vec<Vec<Token>>
     where
         I: IntoIterator<Item = P>,
         P: AsRef<[u8]>,
@@ -30,34 +44,51 @@ impl RouterBuilder {
         for pattern in patterns.into_iter() {
             let mut cur = vec![];
             let bytes = pattern.as_ref();
-            let mut in_brace = false;
-            let mut should_check = true;
+            let mut arg: Option<Vec<u8>> = None;
             let mut last_byte = 0;
 
             for b in bytes.iter() {
                 last_byte = *b;
-                if should_check {
-                    in_brace = *b == b'{';
-                }
-                if in_brace {
-                    should_check = false;
-                    if *b == b'}' {
-                        should_check = true;
-                        cur.push(RESERVED_BYTE);
+                if let Some(a) = arg.as_mut() {
+                    if *b != b'}' {
+                        a.push(*b);
+                        continue;
                     }
+
+                    if a.first() == Some(&b'*') {
+                        cur.push(Token::CatchAll);
+                        self.has_asterisk = true;
+                        arg = None;
+                        continue;
+                    }
+
+                    let constraint = a.iter().position(|b| *b == b':').map(|i| {
+                        let c = String::from_utf8_lossy(&a[i + 1..]);
+                        Constraint::parse(&c).unwrap_or_else(|e| panic!("{}", e))
+                    });
+                    cur.push(Token::Arg(constraint));
+                    arg = None;
+                    continue;
+                }
+                if *b == b'{' {
+                    arg = Some(vec![]);
                     continue;
                 }
-                cur.push(*b);
+                cur.push(Token::Byte(*b));
             }
             if last_byte == b'*' {
                 cur.pop();
-                cur.push(ASTERISK_BYTE);
+                cur.push(Token::Asterisk);
                 self.has_asterisk = true;
             }
             pts.push(cur);
         }
         pts
     }
+
+    /// # Panics
+    /// if a route argument has an invalid constraint
+    /// `app!` validates the constraints at compile time
     pub fn build<I, P>(&mut self, patterns: I) -> Router
     where
         I: IntoIterator<Item = P>,
@@ -66,26 +97,8 @@ impl RouterBuilder {
         let patterns = self.replace(patterns);
         let mut states = make_states();
 
-        for (i, bytes) in patterns.into_iter().enumerate() {
-            let mut state = &mut Default::default();
-            let mut cur_states = &mut states;
-
-            for ch in bytes.iter() {
-                let ch = if self.ascii_case_insensitive {
-                    ascii_to_lower(*ch)
-                } else {
-                    *ch
-                };
-
-                state = &mut cur_states[ch as usize];
-
-                if state.trans.is_none() {
-                    state.trans = Some(make_states());
-                }
-                cur_states = state.trans.as_mut().unwrap();
-            }
-
-            state.match_index = Some(i);
+        for (i, tokens) in patterns.into_iter().enumerate() {
+            self.insert(&mut states, &tokens, i);
         }
 
         let casing = if self.ascii_case_insensitive {
@@ -101,6 +114,53 @@ impl RouterBuilder {
         }
     }
 
+    fn insert(&self, cur_states: &mut [State; 256], tokens: &[Token], index: usize) {
+        let (token, rest) = match tokens.split_first() {
+            Some(t) => t,
+            None => return,
+        };
+
+        let state = match token {
+            Token::Byte(b) => {
+                let ch = if self.ascii_case_insensitive {
+                    ascii_to_lower(*b)
+                } else {
+                    *b
+                };
+                &mut cur_states[ch as usize]
+            }
+            Token::Arg(None) => &mut cur_states[RESERVED_BYTE_INDEX],
+            Token::Arg(Some(constraint)) => {
+                let arg = &mut cur_states[RESERVED_BYTE_INDEX];
+                let pos = match arg.constrained.iter().position(|(c, _)| c == constraint) {
+                    Some(pos) => pos,
+                    None => {
+                        arg.constrained
+                            .push((constraint.clone(), Default::default()));
+                        arg.constrained.len() - 1
+                    }
+                };
+                &mut arg.constrained[pos].1
+            }
+            Token::Asterisk => &mut cur_states[ASTERISK_BYTE_INDEX],
+            Token::CatchAll => &mut cur_states[CATCH_ALL_BYTE_INDEX],
+        };
+
+        if state.trans.is_none() {
+            state.trans = Some(make_states());
+        }
+
+        if rest.is_empty() {
+            // identical patterns are resolved in favor of the first one
+            if state.match_index.is_none() {
+                state.match_index = Some(index);
+            }
+            return;
+        }
+
+        self.insert(state.trans.as_mut().unwrap(), rest, index);
+    }
+
     pub fn ascii_case_insensitive(&mut self, yes: bool) -> &mut RouterBuilder {
         self.ascii_case_insensitive = yes;
         self
@@ -149,6 +209,18 @@ impl Array {
 
         self.array.push(value)
     }
+
+    fn pop(&mut self) {
+        if self.array.len() > ARRAY_DEFAULT_SIZE {
+            self.array.pop();
+            return;
+        }
+
+        if self.i > 0 {
+            self.i -= 1;
+            self.array[self.i] = (0, 0);
+        }
+    }
 }
 
 /// Match represents a matched route
@@ -184,95 +256,190 @@ pub struct Router {
 }
 
 impl Router {
+    /// route finds the pattern that matches the given path
+    /// the precedence does not depend on the registration order
+    /// at every segment, the candidates are tried in the following order
+    /// 1. static bytes `/users/me`
+    /// 2. constrained arguments `/users/{id:u64}`, in registration order
+    /// 3. arguments `/users/{name}`
+    /// 4. the trailing wildcard `/users/*` or `/users/{*rest}`
+    /// `{*rest}` is returned as a single argument that spans the rest of the path
+    /// if a candidate fails further down the path, the next one is tried
     pub fn route<P>(&self, r: P) -> Option<Match>
     where
         P: AsRef<[u8]>,
     {
         let bytes = r.as_ref();
-
-        let mut state;
-        let mut match_index = None;
-        let mut cur_states = &*self.states;
-        let mut i = 0;
         let mut args = Array::new();
         let mut multi_segments = Array::new();
 
-        'outer: loop {
+        let index = self.walk(&self.states, None, bytes, 0, &mut args, &mut multi_segments)?;
+
+        Some(Match {
+            index,
+            args,
+            multi_segments,
+        })
+    }
+
+    /// route_normalized is meant to be used after `route` failed
+    /// it merges repeated slashes and then adds or removes the trailing slash
+    /// returns the first variant of the path that matches, along with the match
+    pub fn route_normalized(&self, path: &str) -> Option<(String, Match)> {
+        let mut merged = String::with_capacity(path.len() + 1);
+        for c in path.chars() {
+            if c == '/' && merged.ends_with('/') {
+                continue;
+            }
+            merged.push(c);
+        }
+
+        if merged != path {
+            if let Some(m) = self.route(&merged) {
+                return Some((merged, m));
+            }
+        }
+
+        if merged.ends_with('/') {
+            if merged.len() == 1 {
+                return None;
+            }
+            merged.pop();
+        } else {
+            merged.push('/');
+        }
+
+        let m = self.route(&merged)?;
+        Some((merged, m))
+    }
+
+    // follows the static bytes as far as possible
+    // and falls back to the route arguments, starting from the longest static prefix
+    fn walk(
+        &self,
+        mut cur_states: &[State; 256],
+        mut match_index: Option<usize>,
+        bytes: &[u8],
+        mut i: usize,
+        args: &mut Array,
+        multi_segments: &mut Array,
+    ) -> Option<usize> {
+        let mut branches: SmallVec<[(&[State; 256], usize); ARRAY_DEFAULT_SIZE]> = SmallVec::new();
+
+        loop {
             if i == bytes.len() {
+                if match_index.is_some() {
+                    return match_index;
+                }
+                break;
+            }
+
+            if self.has_branches(cur_states) {
+                branches.push((cur_states, i));
+            }
+
+            // the reserved bytes never match the placeholders
+            if bytes[i] == RESERVED_BYTE || bytes[i] == ASTERISK_BYTE || bytes[i] == CATCH_ALL_BYTE
+            {
                 break;
             }
 
-            loop {
-                state = &cur_states[self.casing[bytes[i] as usize] as usize];
-                if let Some(trans) = &state.trans {
+            let state = &cur_states[self.casing[bytes[i] as usize] as usize];
+            match &state.trans {
+                Some(trans) => {
                     match_index = state.match_index;
                     cur_states = trans;
                     i += 1;
-                    if i == bytes.len() {
-                        break 'outer;
-                    }
-                } else {
-                    break;
                 }
+                None => break,
             }
+        }
 
-            if self.has_asterisk {
-                if let Some(index) = cur_states[ASTERISK_BYTE_INDEX].match_index {
-                    let mut start = i;
-
-                    loop {
-                        let last = i == bytes.len() - 1;
-                        if bytes[i] == b'/' || last {
-                            i += last as usize;
-                            multi_segments.push((start, i));
-                            start = i + 1;
-                        }
-                        i += 1;
-                        if i >= bytes.len() {
-                            break;
-                        }
-                    }
+        while let Some((cur_states, i)) = branches.pop() {
+            let index = self.branch(cur_states, bytes, i, args, multi_segments);
+            if index.is_some() {
+                return index;
+            }
+        }
 
-                    return Some(Match {
-                        index,
-                        args,
-                        multi_segments,
-                    });
+        None
+    }
+
+    #[inline(always)]
+    fn has_branches(&self, cur_states: &[State; 256]) -> bool {
+        let arg = &cur_states[RESERVED_BYTE_INDEX];
+        arg.trans.is_some()
+            || !arg.constrained.is_empty()
+            || (self.has_asterisk
+                && (cur_states[ASTERISK_BYTE_INDEX].match_index.is_some()
+                    || cur_states[CATCH_ALL_BYTE_INDEX].match_index.is_some()))
+    }
+
+    fn branch(
+        &self,
+        cur_states: &[State; 256],
+        bytes: &[u8],
+        i: usize,
+        args: &mut Array,
+        multi_segments: &mut Array,
+    ) -> Option<usize> {
+        let arg = &cur_states[RESERVED_BYTE_INDEX];
+        let end = bytes[i..]
+            .iter()
+            .position(|b| *b == b'/')
+            .map_or(bytes.len(), |p| i + p);
+
+        for (constraint, state) in arg.constrained.iter() {
+            if let Some(trans) = state.trans.as_deref() {
+                if constraint.matches(&bytes[i..end]) {
+                    args.push((i, end));
+                    let index =
+                        self.walk(trans, state.match_index, bytes, end, args, multi_segments);
+                    if index.is_some() {
+                        return index;
+                    }
+                    args.pop();
                 }
             }
+        }
 
-            let arg = &cur_states[RESERVED_BYTE_INDEX];
+        if let Some(trans) = arg.trans.as_deref() {
+            args.push((i, end));
+            let index = self.walk(trans, arg.match_index, bytes, end, args, multi_segments);
+            if index.is_some() {
+                return index;
+            }
+            args.pop();
+        }
+
+        if self.has_asterisk {
+            if let Some(index) = cur_states[ASTERISK_BYTE_INDEX].match_index {
+                let mut i = i;
+                let mut start = i;
 
-            if let Some(trans) = arg.trans.as_deref() {
-                let start = i;
-                match_index = arg.match_index;
-                cur_states = trans;
-                let mut end;
                 loop {
-                    end = i >= bytes.len();
-                    if end || bytes[i] == b'/' {
-                        break;
+                    let last = i == bytes.len() - 1;
+                    if bytes[i] == b'/' || last {
+                        i += last as usize;
+                        multi_segments.push((start, i));
+                        start = i + 1;
                     }
                     i += 1;
+                    if i >= bytes.len() {
+                        break;
+                    }
                 }
 
-                args.push((start, i));
-                if end {
-                    break 'outer;
-                }
-                continue;
+                return Some(index);
             }
-            return None;
-        }
 
-        match match_index {
-            Some(index) => Some(Match {
-                index,
-                args,
-                multi_segments,
-            }),
-            None => None,
+            if let Some(index) = cur_states[CATCH_ALL_BYTE_INDEX].match_index {
+                args.push((i, bytes.len()));
+                return Some(index);
+            }
         }
+
+        None
     }
 }
 
@@ -280,6 +447,9 @@ impl Router {
 struct State {
     trans: Option<Box<[State; 256]>>,
     match_index: Option<usize>,
+    // only used by the reserved byte state
+    // the arguments with a constraint, in registration order
+    constrained: Vec<(Constraint, State)>,
 }
 
 #[cfg(test)]
@@ -473,6 +643,145 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_constraints() {
+        let route = vec![
+            "/users/{id:u64}",
+            "/users/me",
+            "/users/{slug:[a-z-]+}/posts",
+            "/users/{name}/posts",
+            "/items/{id:uuid}",
+        ];
+        let router = RouterBuilder::new().build(route);
+
+        assert_eq!(
+            Some(Match {
+                index: 0,
+                args: vec_to_array(vec![(7, 9)]),
+                multi_segments: vec_to_array(Default::default()),
+            }),
+            router.route("/users/42")
+        );
+        assert_eq!(1, router.route("/users/me").unwrap().get_index());
+        assert_eq!(None, router.route("/users/petar"));
+        assert_eq!(None, router.route("/users/-1"));
+
+        assert_eq!(
+            Some(Match {
+                index: 2,
+                args: vec_to_array(vec![(7, 12)]),
+                multi_segments: vec_to_array(Default::default()),
+            }),
+            router.route("/users/petar/posts")
+        );
+        assert_eq!(
+            Some(Match {
+                index: 3,
+                args: vec_to_array(vec![(7, 12)]),
+                multi_segments: vec_to_array(Default::default()),
+            }),
+            router.route("/users/Petar/posts")
+        );
+
+        assert_eq!(
+            4,
+            router
+                .route("/items/67e55044-10b1-426f-9247-bb680e5fe0c8")
+                .unwrap()
+                .get_index()
+        );
+        assert_eq!(None, router.route("/items/1"));
+    }
+
+    #[test]
+    fn test_backtracking() {
+        let route = vec!["/hello/world/{id}", "/hello/{name}"];
+        let router = RouterBuilder::new().build(route);
+
+        assert_eq!(
+            Some(Match {
+                index: 1,
+                args: vec_to_array(vec![(7, 12)]),
+                multi_segments: vec_to_array(Default::default()),
+            }),
+            router.route("/hello/world")
+        );
+        assert_eq!(0, router.route("/hello/world/1").unwrap().get_index());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let route = vec![
+            "/files/*",
+            "/files/{name}",
+            "/files/{id:u64}",
+            "/files/index",
+            "/files/index",
+        ];
+        let router = RouterBuilder::new().build(route);
+
+        assert_eq!(3, router.route("/files/index").unwrap().get_index());
+        assert_eq!(2, router.route("/files/42").unwrap().get_index());
+        assert_eq!(1, router.route("/files/readme").unwrap().get_index());
+        assert_eq!(
+            Some(Match {
+                index: 0,
+                args: vec_to_array(Default::default()),
+                multi_segments: vec_to_array(vec![(7, 11), (12, 18)]),
+            }),
+            router.route("/files/docs/readme")
+        );
+    }
+
+    #[test]
+    fn test_catch_all() {
+        let route = vec!["/static/{*path}", "/static/index", "/users/{id}/{*rest}"];
+        let router = RouterBuilder::new().build(route);
+
+        assert_eq!(1, router.route("/static/index").unwrap().get_index());
+        assert_eq!(
+            Some(Match {
+                index: 0,
+                args: vec_to_array(vec![(8, 25)]),
+                multi_segments: vec_to_array(Default::default()),
+            }),
+            router.route("/static/css/site/main.css")
+        );
+        assert_eq!(
+            vec![(7, 9), (10, 15)],
+            router.route("/users/42/a/b/c").unwrap().get_args().to_vec()
+        );
+        assert_eq!(None, router.route("/static/"));
+        assert_eq!(None, router.route("/static"));
+    }
+
+    #[test]
+    fn test_route_normalized() {
+        let route = vec!["/users", "/users/{id}/posts/", "/"];
+        let router = RouterBuilder::new().build(route);
+
+        assert_eq!(None, router.route("/users/"));
+        let (path, m) = router.route_normalized("/users/").unwrap();
+        assert_eq!("/users", path);
+        assert_eq!(0, m.get_index());
+
+        let (path, m) = router.route_normalized("//users//1/posts").unwrap();
+        assert_eq!("/users/1/posts/", path);
+        assert_eq!(1, m.get_index());
+        assert_eq!(vec![(7, 8)], m.get_args().to_vec());
+
+        let (path, _) = router.route_normalized("//").unwrap();
+        assert_eq!("/", path);
+        assert_eq!(None, router.route_normalized("/"));
+        assert_eq!(None, router.route_normalized("/posts/"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_constraint() {
+        RouterBuilder::new().build(vec!["/users/{id:u63}"]);
+    }
+
     #[test]
     fn test_ok() {
         let route = vec![
@@ -811,6 +1120,7 @@ fn make_states() -> Box<[State; 256]> {
     const DEFAULT_STATE: State = State {
         trans: None,
         match_index: None,
+        constrained: Vec::new(),
     };
     let states = [DEFAULT_STATE; 256];
 
@@ -1,13 +1,15 @@
 #![forbid(unsafe_code)]
 
 pub use darpi_code_gen::{
-    app, handler, job_factory, main, middleware, req_formatter, resp_formatter, test, Path, Query,
+    app, handler, job_factory, main, middleware, req_formatter, resp_formatter, test,
+    MultipartFields, Path, Query, ResponderError, Schema,
 };
 pub use darpi_web::{
-    handler::Args, handler::Handler, job, job::RequestJobFactory, job::ResponseJobFactory, logger,
-    logger::ReqFormatter, logger::RespFormatter, middleware::RequestMiddleware,
-    middleware::ResponseMiddleware, oneshot, request, response, response::Responder, spawn,
-    xml::Xml, yaml::Yaml, App, Json,
+    cookie, handler::Args, handler::Handler, job, job::RequestJobFactory, job::ResponseJobFactory,
+    logger, logger::ReqFormatter, logger::RespFormatter, middleware::RequestMiddleware,
+    middleware::ResponseMiddleware, multipart, multipart::Multipart, negotiate, oneshot, openapi,
+    problem, request, response, response::RequestResponder, response::Responder, spawn, sse,
+    sse::Sse, stream, tls, xml::Xml, yaml::Yaml, App, Form, Json, Negotiated, RouteInfo,
 };
 
 pub trait Route<T = ()> {
@@ -0,0 +1,89 @@
+use darpi::header::CONTENT_TYPE;
+use darpi::request::{BodyStream, BodyStreamErr};
+use darpi::{app, handler, App, Body, Response, StatusCode};
+use env_logger;
+use futures::{Future, StreamExt};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn count(#[body] body: BodyStream) -> Result<String, BodyStreamErr> {
+    let mut body = body.limit(1024 * 1024);
+    let mut size = 0;
+    let mut chunks = 0;
+    while let Some(chunk) = body.next().await {
+        size += chunk?.len();
+        chunks += 1;
+    }
+    assert!(chunks > 0);
+    Ok(format!("{}", size))
+}
+
+#[handler]
+async fn proxy(#[body] body: BodyStream) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/octet-stream")
+        .body(body.into_inner())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn body_stream() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post("http://127.0.0.1:3000/count")
+        .body(vec![7u8; 512 * 1024])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("524288", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/count")
+        .body(vec![7u8; 2 * 1024 * 1024])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/proxy")
+        .body("streamed back")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("streamed back", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/count",
+            method: POST,
+            handler: count
+        },
+        {
+            route: "/proxy",
+            method: POST,
+            handler: proxy
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
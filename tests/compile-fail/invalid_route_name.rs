@@ -0,0 +1,23 @@
+use darpi::{app, handler};
+
+#[handler]
+pub(crate) async fn home() -> String {
+    format!("home")
+}
+
+#[darpi::main]
+async fn main() -> Result<(), darpi::Error> {
+    env_logger::builder().is_test(true).try_init().unwrap();
+
+    app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/",
+            method: GET,
+            handler: home,
+            name: "home-page"
+        }]
+    })
+    .run()
+    .await
+}
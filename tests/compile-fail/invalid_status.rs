@@ -0,0 +1,16 @@
+use darpi::ResponderError;
+use std::fmt;
+
+#[derive(Debug, ResponderError)]
+pub enum Error {
+    #[status(1000)]
+    TooBig,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "too big")
+    }
+}
+
+fn main() {}
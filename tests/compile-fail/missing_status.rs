@@ -0,0 +1,17 @@
+use darpi::ResponderError;
+use std::fmt;
+
+#[derive(Debug, ResponderError)]
+pub enum Error {
+    #[status(404)]
+    NotFound,
+    Unknown,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error")
+    }
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+use darpi::{app, handler, App, Path};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct User {
+    pub name: String,
+}
+
+#[handler]
+pub(crate) async fn get_user(#[path] user: User) -> String {
+    user.name
+}
+
+#[darpi::main]
+async fn main() -> Result<(), darpi::Error> {
+    env_logger::builder().is_test(true).try_init().unwrap();
+
+    app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/user/{id}",
+            method: GET,
+            handler: get_user,
+            name: "user_detail"
+        }]
+    })
+    .run()
+    .await
+}
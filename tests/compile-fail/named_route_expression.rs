@@ -0,0 +1,18 @@
+use darpi::app;
+
+#[darpi::main]
+async fn main() -> Result<(), darpi::Error> {
+    env_logger::builder().is_test(true).try_init().unwrap();
+
+    app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/static/{*path}",
+            method: GET,
+            handler: serve_dir("./public"),
+            name: "assets"
+        }]
+    })
+    .run()
+    .await
+}
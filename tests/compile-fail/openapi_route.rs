@@ -0,0 +1,25 @@
+use darpi::{app, handler};
+
+#[handler]
+pub(crate) async fn docs() -> String {
+    format!("docs")
+}
+
+#[darpi::main]
+async fn main() -> Result<(), darpi::Error> {
+    env_logger::builder().is_test(true).try_init().unwrap();
+
+    app!({
+        address: "127.0.0.1:3000",
+        openapi: {
+            route: "/openapi.json"
+        },
+        handlers: [{
+            route: "/openapi.json",
+            method: GET,
+            handler: docs
+        }]
+    })
+    .run()
+    .await
+}
@@ -0,0 +1,11 @@
+use darpi::Schema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Schema)]
+#[serde(tag = "kind")]
+pub enum Event {
+    Created,
+    Deleted,
+}
+
+fn main() {}
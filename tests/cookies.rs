@@ -0,0 +1,245 @@
+use darpi::cookie::{Cookie, CookieJar, Key, SameSite, SetCookie};
+use darpi::header::{COOKIE, SET_COOKIE};
+use darpi::{app, handler, App, Json, Responder, StatusCode};
+use darpi_middleware::cookie::{
+    CookieKeyProvider, CookieKeyProviderImpl, CookieKeyProviderImplParameters,
+};
+use env_logger;
+use futures::Future;
+use shaku::module;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+module! {
+    Container {
+        components = [CookieKeyProviderImpl],
+        providers = [],
+    }
+}
+
+fn make_container() -> Container {
+    Container::builder()
+        .with_component_parameters::<CookieKeyProviderImpl>(CookieKeyProviderImplParameters {
+            key: Key::from_master(&[7; 64]).unwrap(),
+        })
+        .build()
+}
+
+#[handler]
+async fn visit(#[cookies] mut jar: CookieJar) -> impl Responder {
+    let visits: u32 = jar
+        .get("visits")
+        .and_then(|c| c.value().parse().ok())
+        .unwrap_or(0);
+    jar.add(Cookie::new("visits", (visits + 1).to_string()));
+    format!("visit {}", visits + 1).cookies(jar)
+}
+
+#[handler]
+async fn theme() -> impl Responder {
+    Json::new("dark")
+        .header(
+            SET_COOKIE,
+            Cookie::new("theme", "dark; bold")
+                .path("/")
+                .max_age(Duration::from_secs(60))
+                .same_site(SameSite::None)
+                .into(),
+        )
+        .cookie(Cookie::new("lang", "en").http_only(true))
+}
+
+#[handler]
+async fn files() -> impl Responder {
+    "files".cookie(Cookie::new("dir", "latest").path("/café"))
+}
+
+#[handler]
+async fn logout(#[cookies] mut jar: CookieJar) -> impl Responder {
+    jar.remove(Cookie::new("session", "").path("/"));
+    "bye".cookies(jar)
+}
+
+#[handler({
+    container: Container
+})]
+async fn login(
+    #[inject] keys: Arc<dyn CookieKeyProvider>,
+    #[cookies] mut jar: CookieJar,
+) -> impl Responder {
+    jar.add_signed(keys.key(), Cookie::new("user", "alice"));
+    jar.add_private(keys.key(), Cookie::new("session", "s3cr3t"));
+    "welcome".cookies(jar)
+}
+
+#[handler({
+    container: Container
+})]
+async fn whoami(#[inject] keys: Arc<dyn CookieKeyProvider>, #[cookies] jar: CookieJar) -> String {
+    let user = jar.get_signed(keys.key(), "user");
+    let session = jar.get_private(keys.key(), "session");
+    format!(
+        "{:?} {:?}",
+        user.as_ref().map(|c| c.value()),
+        session.as_ref().map(|c| c.value())
+    )
+}
+
+#[tokio::test]
+async fn cookies() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/visit")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("visits=1", resp.headers().get(SET_COOKIE).unwrap());
+    assert_eq!("visit 1", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/visit")
+        .header(COOKIE, "other=x; visits=41")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("visits=42", resp.headers().get(SET_COOKIE).unwrap());
+    assert_eq!("visit 42", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/theme")
+        .send()
+        .await
+        .unwrap();
+    let set: Vec<_> = resp.headers().get_all(SET_COOKIE).iter().collect();
+    assert_eq!(
+        vec![
+            "theme=dark%3B%20bold; Path=/; Max-Age=60; Secure; SameSite=None",
+            "lang=en; HttpOnly"
+        ],
+        set
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/files")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "dir=latest; Path=/caf%C3%A9",
+        resp.headers().get(SET_COOKIE).unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/logout")
+        .header(COOKIE, "session=abc")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "session=; Path=/; Max-Age=0",
+        resp.headers().get(SET_COOKIE).unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/login")
+        .send()
+        .await
+        .unwrap();
+    let set: Vec<String> = resp
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .map(|hv| hv.to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(2, set.len());
+    assert!(set[0].starts_with("user=") && set[0].ends_with("alice"));
+    assert!(set[1].starts_with("session=") && !set[1].contains("s3cr3t"));
+
+    let resp = client
+        .get("http://127.0.0.1:3000/whoami")
+        .header(COOKIE, set.join("; "))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "Some(\"alice\") Some(\"s3cr3t\")",
+        resp.text().await.unwrap()
+    );
+
+    // tampered values and values moved to another cookie are rejected
+    let tampered = set[0].replace("alice", "admin");
+    let swapped = set[1].replacen("session=", "user=", 1);
+    let resp = client
+        .get("http://127.0.0.1:3000/whoami")
+        .header(COOKIE, format!("{}; {}", tampered, set[1]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("None Some(\"s3cr3t\")", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/whoami")
+        .header(COOKIE, format!("{}; session=garbage", swapped))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("None None", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        container: {
+            factory: make_container(),
+            type: Container
+        },
+        handlers: [{
+            route: "/visit",
+            method: GET,
+            handler: visit
+        },
+        {
+            route: "/theme",
+            method: GET,
+            handler: theme
+        },
+        {
+            route: "/files",
+            method: GET,
+            handler: files
+        },
+        {
+            route: "/logout",
+            method: GET,
+            handler: logout
+        },
+        {
+            route: "/login",
+            method: GET,
+            handler: login
+        },
+        {
+            route: "/whoami",
+            method: GET,
+            handler: whoami
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
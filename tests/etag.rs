@@ -0,0 +1,337 @@
+use darpi::header::{
+    CONTENT_ENCODING, ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE,
+    LAST_MODIFIED,
+};
+use darpi::problem::Problem;
+use darpi::stream::Stream;
+use darpi::{app, handler, App, Body, RequestParts, Response, StatusCode};
+use darpi_middleware::etag::{conditional, etag, Preconditions, Strength};
+use env_logger;
+use futures::Future;
+use std::convert::Infallible;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler({
+    middleware: {
+        request: [conditional()],
+        response: [etag(Strength::Strong, request(0))]
+    }
+})]
+async fn doc() -> &'static str {
+    "hello etag"
+}
+
+#[handler({
+    middleware: {
+        request: [conditional()],
+        response: [etag(Strength::Weak, request(0))]
+    }
+})]
+async fn weak_doc() -> &'static str {
+    "hello etag"
+}
+
+#[handler({
+    middleware: {
+        request: [conditional()],
+        response: [etag(Strength::Strong, request(0))]
+    }
+})]
+async fn versioned() -> Response<Body> {
+    Response::builder()
+        .header(ETAG, "\"v1\"")
+        .header(LAST_MODIFIED, "Wed, 21 Oct 2015 07:28:00 GMT")
+        .body(Body::from("version 1"))
+        .unwrap()
+}
+
+#[handler({
+    middleware: {
+        request: [conditional()],
+        response: [etag(Strength::Strong, request(0))]
+    }
+})]
+async fn precompressed() -> Response<Body> {
+    Response::builder()
+        .header(ETAG, "\"a,b\"")
+        .header(CONTENT_ENCODING, "gzip")
+        .body(Body::from(vec![0x1f, 0x8b]))
+        .unwrap()
+}
+
+#[handler({
+    middleware: {
+        request: [conditional()],
+        response: [etag(Strength::Strong, request(0))]
+    }
+})]
+async fn stream() -> Stream {
+    let chunks = vec!["hello", " ", "stream"]
+        .into_iter()
+        .map(Ok::<_, Infallible>);
+    Stream::bytes(futures::stream::iter(chunks))
+}
+
+#[handler]
+async fn update(#[request_parts] rp: &RequestParts) -> Result<&'static str, Problem> {
+    if let Some(status) = Preconditions::new(&rp.method, &rp.headers).evaluate(Some("\"v1\""), None)
+    {
+        return Err(Problem::new(status));
+    }
+    Ok("updated")
+}
+
+#[tokio::test]
+async fn etag_middleware() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    let tag = resp
+        .headers()
+        .get(ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(tag.starts_with("\"a-") && tag.ends_with('"'));
+    assert_eq!("hello etag", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_NONE_MATCH, format!("\"other\", {}", tag))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+    assert_eq!(tag, resp.headers().get(ETAG).unwrap().to_str().unwrap());
+    assert_eq!("", resp.text().await.unwrap());
+
+    // If-None-Match uses the weak comparison
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_NONE_MATCH, format!("W/{}", tag))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .head("http://127.0.0.1:3000/doc")
+        .header(IF_NONE_MATCH, &tag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_NONE_MATCH, "\"other\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("hello etag", resp.text().await.unwrap());
+
+    // If-Match uses the strong comparison
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_MATCH, &tag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_MATCH, format!("W/{}", tag))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/doc")
+        .header(IF_MATCH, "*")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/weak_doc")
+        .send()
+        .await
+        .unwrap();
+    let weak = resp
+        .headers()
+        .get(ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(format!("W/{}", tag), weak);
+
+    let resp = client
+        .get("http://127.0.0.1:3000/weak_doc")
+        .header(IF_NONE_MATCH, &weak)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    // the ETag and Last-Modified of the handler are kept
+    let resp = client
+        .get("http://127.0.0.1:3000/versioned")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("\"v1\"", resp.headers().get(ETAG).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/versioned")
+        .header(IF_MODIFIED_SINCE, "Thu, 22 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/versioned")
+        .header(IF_MODIFIED_SINCE, "Tue, 20 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    // If-None-Match takes precedence over If-Modified-Since
+    let resp = client
+        .get("http://127.0.0.1:3000/versioned")
+        .header(IF_NONE_MATCH, "\"v0\"")
+        .header(IF_MODIFIED_SINCE, "Thu, 22 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/versioned")
+        .header(IF_UNMODIFIED_SINCE, "Tue, 20 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status());
+
+    // the ETag of the handler is made specific to the encoding
+    let resp = client
+        .get("http://127.0.0.1:3000/precompressed")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("\"a,b-gzip\"", resp.headers().get(ETAG).unwrap());
+
+    // commas inside the quotes are part of the tag
+    let resp = client
+        .get("http://127.0.0.1:3000/precompressed")
+        .header(IF_NONE_MATCH, "\"a\", \"a,b-gzip\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/precompressed")
+        .header(IF_MATCH, "\"a,b\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status());
+
+    // streamed bodies are not buffered
+    let resp = client
+        .get("http://127.0.0.1:3000/stream")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.headers().get(ETAG).is_none());
+    assert_eq!("hello stream", resp.text().await.unwrap());
+
+    let resp = client
+        .put("http://127.0.0.1:3000/update")
+        .header(IF_MATCH, "\"v0\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status());
+
+    let resp = client
+        .put("http://127.0.0.1:3000/update")
+        .header(IF_NONE_MATCH, "*")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PRECONDITION_FAILED, resp.status());
+
+    let resp = client
+        .put("http://127.0.0.1:3000/update")
+        .header(IF_MATCH, "\"v1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("updated", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/doc",
+            method: GET,
+            handler: doc
+        },
+        {
+            route: "/weak_doc",
+            method: GET,
+            handler: weak_doc
+        },
+        {
+            route: "/versioned",
+            method: GET,
+            handler: versioned
+        },
+        {
+            route: "/precompressed",
+            method: GET,
+            handler: precompressed
+        },
+        {
+            route: "/stream",
+            method: GET,
+            handler: stream
+        },
+        {
+            route: "/update",
+            method: PUT,
+            handler: update
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
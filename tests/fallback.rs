@@ -0,0 +1,65 @@
+use darpi::response::ResponderError;
+use darpi::{app, handler, App, RequestParts, StatusCode};
+use derive_more::Display;
+use env_logger;
+use futures::Future;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn hello_world_handler() -> &'static str {
+    "hello world"
+}
+
+#[derive(Display)]
+#[display(fmt = "could not find: {}", _0)]
+pub struct NotFound(String);
+
+impl ResponderError for NotFound {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[handler]
+async fn not_found(#[request_parts] rp: &RequestParts) -> Result<(), NotFound> {
+    Err(NotFound(rp.uri.path().to_string()))
+}
+
+#[tokio::test]
+async fn fallback() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = reqwest::get("http://127.0.0.1:3000/hello_world")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("hello world", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/unknown").await.unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    assert_eq!("could not find: /unknown", resp.text().await.unwrap());
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/hello_world",
+            method: GET,
+            handler: hello_world_handler
+        }],
+        fallback: not_found
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,122 @@
+use darpi::header::CONTENT_TYPE;
+use darpi::{app, handler, App, Form, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Login {
+    pub name: String,
+    pub remember: bool,
+}
+
+#[handler]
+async fn login(#[body] l: Form<Login>) -> String {
+    format!("{} {}", l.name, l.remember)
+}
+
+#[handler]
+async fn small(#[body] l: Form<Login, 32>) -> Form<Login> {
+    Form::new(l.into_inner())
+}
+
+#[tokio::test]
+async fn form() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post("http://127.0.0.1:3000/login")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("name=john+doe&remember=true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("john doe true", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/login")
+        .header(
+            CONTENT_TYPE,
+            "application/x-www-form-urlencoded; charset=utf-8",
+        )
+        .body("name=john&remember=false")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("john false", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/login")
+        .header(CONTENT_TYPE, "application/json")
+        .body(r#"{"name":"john","remember":true}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/login")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("name=john")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/small")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("name=jo&remember=true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "application/x-www-form-urlencoded",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("name=jo&remember=true", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/small")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body("name=a+very+long+name+indeed&remember=true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/login",
+            method: POST,
+            handler: login
+        },
+        {
+            route: "/small",
+            method: POST,
+            handler: small
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
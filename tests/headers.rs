@@ -0,0 +1,180 @@
+use darpi::header::{AUTHORIZATION, CONTENT_TYPE, IF_NONE_MATCH, USER_AGENT};
+use darpi::{app, handler, App, Body, Response, StatusCode};
+use darpi_headers::{Authorization, Basic, Bearer, ContentType, Host, IfNoneMatch, UserAgent};
+use env_logger;
+use futures::Future;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn bearer(#[header] auth: Authorization<Bearer>) -> String {
+    format!("token {}", auth.token())
+}
+
+#[handler]
+async fn basic(#[header] auth: Authorization<Basic>) -> String {
+    format!("{}:{}", auth.username(), auth.password())
+}
+
+#[handler]
+async fn agent(#[header] ua: Option<UserAgent>, #[header] host: Host) -> String {
+    let ua = ua.as_ref().map_or("unknown", |ua| ua.as_str());
+    format!("{} {} {:?}", ua, host.hostname(), host.port())
+}
+
+#[handler]
+async fn cached(#[header] inm: Option<IfNoneMatch>) -> Response<Body> {
+    let status = match inm {
+        Some(inm) if inm.matches("\"v1\"") => StatusCode::NOT_MODIFIED,
+        _ => StatusCode::OK,
+    };
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[handler]
+async fn upload(#[header] ct: ContentType, #[body] body: String) -> String {
+    format!("{} {:?} {}", ct.mime(), ct.param("charset"), body)
+}
+
+#[tokio::test]
+async fn headers() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/bearer")
+        .header(AUTHORIZATION, "bearer abc.def")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("token abc.def", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/bearer")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    assert_eq!("missing header `authorization`", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/bearer")
+        .header(AUTHORIZATION, "Basic YWxpY2U6c2VjcmV0")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    assert_eq!(
+        "invalid header `authorization`: expected the `Bearer` scheme",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/basic")
+        .basic_auth("alice", Some("secret"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("alice:secret", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/agent")
+        .header(USER_AGENT, "darpi-test")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "darpi-test 127.0.0.1 Some(3000)",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/agent")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("unknown 127.0.0.1 Some(3000)", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/cached")
+        .header(IF_NONE_MATCH, "\"v0\", W/\"v1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/cached")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    // an invalid optional header is still rejected
+    let resp = client
+        .get("http://127.0.0.1:3000/cached")
+        .header(IF_NONE_MATCH, "v1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "text/plain; charset=UTF-8")
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "text/plain Some(\"UTF-8\") hello",
+        resp.text().await.unwrap()
+    );
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/bearer",
+            method: GET,
+            handler: bearer
+        },
+        {
+            route: "/basic",
+            method: GET,
+            handler: basic
+        },
+        {
+            route: "/agent",
+            method: GET,
+            handler: agent
+        },
+        {
+            route: "/cached",
+            method: GET,
+            handler: cached
+        },
+        {
+            route: "/upload",
+            method: POST,
+            handler: upload
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,116 @@
+use darpi::header::{HeaderValue, ALLOW};
+use darpi::{app, handler, middleware, App, Body, Path, Response, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct User {
+    pub id: String,
+}
+
+#[middleware(Response)]
+async fn stamp(#[response] r: &mut Response<Body>) -> Result<(), Infallible> {
+    r.headers_mut()
+        .insert("x-stamp", HeaderValue::from_static("stamped"));
+    Ok(())
+}
+
+#[handler]
+async fn get_user(#[path] u: User) -> String {
+    format!("get {}", u.id)
+}
+
+#[handler]
+async fn update_user(#[path] u: User) -> String {
+    format!("update {}", u.id)
+}
+
+#[tokio::test]
+async fn method_not_allowed() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/user/1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("get 1", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/user/1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("update 1", resp.text().await.unwrap());
+
+    let resp = client
+        .delete("http://127.0.0.1:3000/user/1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+    assert_eq!(
+        "GET, POST, HEAD, OPTIONS",
+        resp.headers().get(ALLOW).unwrap()
+    );
+    assert_eq!("stamped", resp.headers().get("x-stamp").unwrap());
+
+    let resp = client
+        .request(reqwest::Method::OPTIONS, "http://127.0.0.1:3000/user/1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NO_CONTENT, resp.status());
+    assert_eq!(
+        "GET, POST, HEAD, OPTIONS",
+        resp.headers().get(ALLOW).unwrap()
+    );
+    assert_eq!("stamped", resp.headers().get("x-stamp").unwrap());
+
+    let resp = client
+        .delete("http://127.0.0.1:3000/unknown")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    assert!(resp.headers().get(ALLOW).is_none());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        middleware: {
+            response: [stamp]
+        },
+        handlers: [{
+            route: "/user/{id}",
+            method: GET,
+            handler: get_user
+        },
+        {
+            route: "/user/{id}",
+            method: POST,
+            handler: update_user
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
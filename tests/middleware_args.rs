@@ -0,0 +1,67 @@
+use darpi::header::HeaderValue;
+use darpi::{app, handler, middleware, App, Body, Request, Response};
+use env_logger;
+use futures::Future;
+use std::convert::Infallible;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[middleware(Request)]
+async fn tag(
+    #[request] _r: &Request<Body>,
+    #[handler] name: &'static str,
+) -> Result<String, Infallible> {
+    Ok(name.to_string())
+}
+
+#[middleware(Response)]
+async fn stamp(
+    #[response] r: &mut Response<Body>,
+    #[handler] tag: String,
+) -> Result<(), Infallible> {
+    r.headers_mut()
+        .insert("x-tag", HeaderValue::from_str(&tag).unwrap());
+    Ok(())
+}
+
+#[handler({
+    middleware: {
+        request: [tag("first"), tag("second")],
+        response: [stamp(request(1))]
+    }
+})]
+async fn tagged() -> &'static str {
+    "tagged"
+}
+
+#[tokio::test]
+async fn middleware_args() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = reqwest::get("http://127.0.0.1:3000/tagged").await.unwrap();
+    assert_eq!("second", resp.headers().get("x-tag").unwrap());
+    assert_eq!("tagged", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/tagged",
+            method: GET,
+            handler: tagged
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,201 @@
+use darpi::body::Bytes;
+use darpi::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use darpi::multipart::{Multipart, MultipartErr};
+use darpi::{app, handler, App, Body, MultipartFields, StatusCode};
+use env_logger;
+use futures::Future;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+const BODY: &str = "preamble\r\n--XyZ\r\n\
+    Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+    hello\r\nworld\r\n\
+    --XyZ\r\n\
+    Content-Disposition: form-data; name=\"file\"; filename=\"a;b.txt\"\r\n\
+    Content-Type: text/plain\r\n\r\n\
+    --XyZ is not a delimiter\r\n\
+    --XyZ--\r\n";
+
+fn multipart(body: &'static str, chunk: usize) -> Multipart {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("multipart/form-data; boundary=XyZ"),
+    );
+
+    let chunks: Vec<Result<Bytes, std::io::Error>> = body
+        .as_bytes()
+        .chunks(chunk)
+        .map(|c| Ok(Bytes::copy_from_slice(c)))
+        .collect();
+    let body = Body::wrap_stream(futures::stream::iter(chunks));
+    Multipart::new(&headers, body).unwrap()
+}
+
+#[tokio::test]
+async fn multipart_parts() {
+    // every chunk size splits the delimiter at a different position
+    for chunk in 1..BODY.len() {
+        let mut mp = multipart(BODY, chunk);
+
+        let part = mp.next_part().await.unwrap().unwrap();
+        assert_eq!("title", part.name());
+        assert_eq!(None, part.file_name());
+        assert_eq!("hello\r\nworld", part.text().await.unwrap());
+
+        let part = mp.next_part().await.unwrap().unwrap();
+        assert_eq!("file", part.name());
+        assert_eq!(Some("a;b.txt"), part.file_name());
+        assert_eq!(Some("text/plain"), part.content_type());
+        assert_eq!("--XyZ is not a delimiter", part.text().await.unwrap());
+
+        assert!(mp.next_part().await.unwrap().is_none());
+    }
+
+    let mut mp = multipart(BODY, 7);
+    let _ = mp.next_part().await.unwrap().unwrap();
+    let part = mp.next_part().await.unwrap().unwrap();
+    assert_eq!("file", part.name());
+}
+
+#[tokio::test]
+async fn multipart_limits() {
+    let mut mp = multipart(BODY, 16).part_limit(8);
+    let part = mp.next_part().await.unwrap().unwrap();
+    assert!(matches!(
+        part.text().await,
+        Err(MultipartErr::PartSize(_, 8))
+    ));
+
+    let mut mp = multipart(BODY, 16).total_limit(32);
+    assert!(matches!(
+        mp.next_part().await,
+        Err(MultipartErr::TotalSize(32))
+    ));
+
+    let mut mp = multipart(
+        "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nab",
+        4,
+    );
+    let part = mp.next_part().await.unwrap().unwrap();
+    assert!(matches!(part.text().await, Err(MultipartErr::Incomplete)));
+}
+
+#[derive(MultipartFields)]
+pub struct Upload {
+    pub title: String,
+    pub count: u32,
+    pub note: Option<String>,
+}
+
+#[handler]
+async fn upload(#[body] mp: Multipart) -> Result<String, MultipartErr> {
+    let dir = std::env::temp_dir();
+    let form = mp.part_limit(64).spool_dir(&dir).collect().await?;
+    let meta: Upload = form.parse()?;
+
+    let file = form.file("file").unwrap();
+    let content = std::fs::read_to_string(file.path()).unwrap();
+    assert!(file.path().starts_with(&dir));
+
+    Ok(format!(
+        "{} {} {:?} {} {} {}",
+        meta.title,
+        meta.count,
+        meta.note,
+        file.file_name().unwrap(),
+        file.size(),
+        content
+    ))
+}
+
+#[tokio::test]
+async fn multipart_upload() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let body = "--b0undary\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        report\r\n\
+        --b0undary\r\n\
+        Content-Disposition: form-data; name=\"count\"\r\n\r\n\
+        3\r\n\
+        --b0undary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"report.csv\"\r\n\
+        Content-Type: text/csv\r\n\r\n\
+        a,b\n1,2\r\n\
+        --b0undary--\r\n";
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "multipart/form-data; boundary=b0undary")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "report 3 None report.csv 7 a,b\n1,2",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "multipart/form-data; boundary=b0undary")
+        .body(body.replace("\r\n3\r\n", "\r\nthree\r\n"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "multipart/form-data; boundary=b0undary")
+        .body(body.replace("a,b\n1,2", &"x".repeat(65)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "multipart/form-data")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/upload")
+        .header(CONTENT_TYPE, "text/plain")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/upload",
+            method: POST,
+            handler: upload
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
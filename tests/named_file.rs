@@ -0,0 +1,114 @@
+use darpi::header::{CONTENT_DISPOSITION, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE};
+use darpi::{app, handler, App, RequestParts, StatusCode};
+use darpi_files::{NamedFile, NamedFileErr};
+use env_logger;
+use futures::Future;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn digits(#[request_parts] rp: &RequestParts) -> Result<NamedFile, NamedFileErr> {
+    let file = NamedFile::open("tests/static/digits.txt").await?;
+    Ok(file.with_request(&rp.headers))
+}
+
+#[handler]
+async fn report(#[request_parts] rp: &RequestParts) -> Result<NamedFile, NamedFileErr> {
+    let file = NamedFile::open("tests/static/digits.txt").await?;
+    Ok(file
+        .attachment()
+        .file_name("rapport été.txt")
+        .with_request(&rp.headers))
+}
+
+#[handler]
+async fn missing() -> Result<NamedFile, NamedFileErr> {
+    NamedFile::open("tests/static/missing.txt").await
+}
+
+#[tokio::test]
+async fn named_file() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/digits")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "text/plain; charset=utf-8",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!(
+        "inline; filename=\"digits.txt\"",
+        resp.headers().get(CONTENT_DISPOSITION).unwrap()
+    );
+    let etag = resp.headers().get(ETAG).unwrap().clone();
+    assert_eq!("0123456789", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/digits")
+        .header(IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+    assert_eq!("", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/report")
+        .header(RANGE, "bytes=7-")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+    assert_eq!("bytes 7-9/10", resp.headers().get(CONTENT_RANGE).unwrap());
+    assert_eq!(
+        "attachment; filename=\"rapport _t_.txt\"; filename*=UTF-8''rapport%20%C3%A9t%C3%A9.txt",
+        resp.headers().get(CONTENT_DISPOSITION).unwrap()
+    );
+    assert_eq!("789", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/missing")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/digits",
+            method: GET,
+            handler: digits
+        },
+        {
+            route: "/report",
+            method: GET,
+            handler: report
+        },
+        {
+            route: "/missing",
+            method: GET,
+            handler: missing
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
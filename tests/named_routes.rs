@@ -0,0 +1,121 @@
+use darpi::header::LOCATION;
+use darpi::{app, handler, App, Body, Path, Response, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct User {
+    pub id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct File {
+    pub name: String,
+    pub folder: String,
+}
+
+#[handler]
+async fn get_user(#[path] u: User) -> String {
+    format!("user {}", u.id)
+}
+
+#[handler]
+async fn create_user() -> Response<Body> {
+    let location = get_user::user_detail_url(&User { id: 7 });
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header(LOCATION, location)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[handler]
+async fn get_file(#[path] f: File) -> String {
+    format!("{}/{}", f.folder, f.name)
+}
+
+#[handler]
+async fn links() -> String {
+    let file = File {
+        folder: "my docs".to_string(),
+        name: "a/b".to_string(),
+    };
+    format!("{} {}", links::home_url(&()), get_file::file_url(&file))
+}
+
+#[tokio::test]
+async fn named_routes() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let resp = client
+        .post("http://127.0.0.1:3000/api/users")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::CREATED, resp.status());
+    assert_eq!("/api/users/7", resp.headers().get(LOCATION).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/api/users/7")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("user 7", resp.text().await.unwrap());
+
+    let resp = client.get("http://127.0.0.1:3000/").send().await.unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("/ /files/my%20docs/a%2Fb", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/",
+            method: GET,
+            handler: links,
+            name: "home"
+        },
+        {
+            route: "/files/{folder}/{name}",
+            method: GET,
+            handler: get_file,
+            name: "file"
+        },
+        {
+            scope: "/api",
+            handlers: [{
+                route: "/users",
+                method: POST,
+                handler: create_user
+            },
+            {
+                route: "/users/{id:u64}",
+                method: GET,
+                handler: get_user,
+                name: "user_detail"
+            }]
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,164 @@
+use darpi::header::{ACCEPT, CONTENT_TYPE, VARY};
+use darpi::{app, handler, App, Negotiated, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct User {
+    pub name: String,
+    pub age: u32,
+}
+
+#[handler]
+async fn profile() -> Negotiated<User> {
+    Negotiated::new(User {
+        name: "john".to_string(),
+        age: 42,
+    })
+}
+
+#[handler]
+async fn echo(#[body] mut user: Negotiated<User>) -> Negotiated<User> {
+    user.age += 1;
+    user
+}
+
+#[tokio::test]
+async fn negotiate() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/profile")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "application/json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("accept", resp.headers().get(VARY).unwrap());
+    assert_eq!("{\"name\":\"john\",\"age\":42}", resp.text().await.unwrap());
+
+    let cases = vec![
+        ("application/json", "application/json"),
+        ("text/xml", "application/xml"),
+        ("application/x-yaml", "application/yaml"),
+        (
+            "text/html, application/xml;q=0.9, */*;q=0.8",
+            "application/xml",
+        ),
+        (
+            "application/json;q=0.5, application/yaml",
+            "application/yaml",
+        ),
+        (
+            "application/*;q=0.5, application/xml;q=0.7",
+            "application/xml",
+        ),
+        ("*/*, application/json;q=0", "application/xml"),
+        ("*/*", "application/json"),
+    ];
+    for (accept, expected) in cases {
+        let resp = client
+            .get("http://127.0.0.1:3000/profile")
+            .header(ACCEPT, accept)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status(), "{}", accept);
+        assert_eq!(
+            expected,
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "{}",
+            accept
+        );
+    }
+
+    let resp = client
+        .get("http://127.0.0.1:3000/profile")
+        .header(ACCEPT, "application/yaml")
+        .send()
+        .await
+        .unwrap();
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("name: john") && body.contains("age: 42"));
+
+    let resp = client
+        .get("http://127.0.0.1:3000/profile")
+        .header(ACCEPT, "text/html, application/json;q=0")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_ACCEPTABLE, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/echo")
+        .header(CONTENT_TYPE, "application/xml")
+        .header(ACCEPT, "application/json")
+        .body("<User><name>jane</name><age>30</age></User>")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("{\"name\":\"jane\",\"age\":31}", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/echo")
+        .header(CONTENT_TYPE, "application/yaml; charset=utf-8")
+        .header(ACCEPT, "application/json")
+        .body("name: jane\nage: 30\n")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("{\"name\":\"jane\",\"age\":31}", resp.text().await.unwrap());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/echo")
+        .header(CONTENT_TYPE, "text/plain")
+        .body("jane")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/echo")
+        .header(CONTENT_TYPE, "application/json")
+        .body("{\"name\": \"jane\"}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/profile",
+            method: GET,
+            handler: profile
+        },
+        {
+            route: "/echo",
+            method: POST,
+            handler: echo
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
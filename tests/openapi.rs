@@ -0,0 +1,232 @@
+use darpi::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use darpi::{app, handler, App, Json, Path, Query, Schema, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path, Schema)]
+pub struct UserId {
+    pub id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Query, Schema)]
+pub struct Page {
+    pub limit: u32,
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Schema)]
+pub enum Role {
+    Admin,
+    Member,
+}
+
+#[derive(Deserialize, Serialize, Debug, Schema)]
+pub struct User {
+    pub name: String,
+    pub roles: Vec<Role>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Schema)]
+pub struct Links {
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    #[serde(rename = "total")]
+    pub item_count: u64,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(flatten)]
+    pub links: Links,
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+#[handler({
+    openapi: {
+        summary: "get a user",
+        response: Json<User>
+    }
+})]
+async fn get_user(#[path] _id: UserId) -> Json<User> {
+    Json::new(User {
+        name: "john".to_string(),
+        roles: vec![Role::Member],
+    })
+}
+
+#[handler({
+    openapi: {
+        summary: "list users",
+        response: Json<Paged<User>>
+    }
+})]
+async fn list_users(#[query] _page: Page) -> Json<Paged<User>> {
+    Json::new(Paged {
+        items: vec![],
+        item_count: 0,
+        has_more: false,
+        links: Links { next: None },
+        cached: false,
+    })
+}
+
+#[handler({
+    openapi: {}
+})]
+async fn create_user(#[body] user: Json<User>) -> String {
+    user.name.clone()
+}
+
+#[handler]
+async fn health() -> &'static str {
+    "ok"
+}
+
+#[tokio::test]
+async fn openapi() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = reqwest::get("http://127.0.0.1:3000/openapi.json")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "application/json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+
+    let len = resp.headers().get(CONTENT_LENGTH).unwrap().clone();
+    let doc: Value = resp.json().await.unwrap();
+    assert_eq!("3.0.3", doc["openapi"]);
+    assert_eq!(json!({"title": "users", "version": "1.0.0"}), doc["info"]);
+
+    let user_schema = json!({
+        "type": "object",
+        "properties": {
+            "name": {"type": "string"},
+            "roles": {"type": "array", "items": {"type": "string", "enum": ["Admin", "Member"]}},
+        },
+        "required": ["name", "roles"],
+    });
+
+    let get = &doc["paths"]["/users/{id}"]["get"];
+    assert_eq!("get_user", get["operationId"]);
+    assert_eq!("get a user", get["summary"]);
+    assert_eq!(
+        json!([{
+            "name": "id",
+            "in": "path",
+            "required": true,
+            "schema": {"type": "integer", "format": "int64", "minimum": 0},
+        }]),
+        get["parameters"]
+    );
+    assert_eq!(
+        user_schema,
+        get["responses"]["200"]["content"]["application/json"]["schema"]
+    );
+
+    let list = &doc["paths"]["/users"]["get"];
+    assert_eq!("list_users", list["operationId"]);
+    assert_eq!(
+        json!([{
+            "name": "cursor",
+            "in": "query",
+            "required": false,
+            "schema": {"type": "string"},
+        }, {
+            "name": "limit",
+            "in": "query",
+            "required": true,
+            "schema": {"type": "integer", "format": "int64", "minimum": 0},
+        }]),
+        list["parameters"]
+    );
+    assert_eq!(
+        json!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": user_schema},
+                "total": {"type": "integer", "format": "int64", "minimum": 0},
+                "hasMore": {"type": "boolean"},
+                "next": {"type": "string"},
+            },
+            "required": ["items", "total"],
+        }),
+        list["responses"]["200"]["content"]["application/json"]["schema"]
+    );
+
+    let create = &doc["paths"]["/users"]["post"];
+    assert_eq!("create_user", create["operationId"]);
+    assert_eq!(
+        user_schema,
+        create["requestBody"]["content"]["application/json"]["schema"]
+    );
+
+    let health_op = &doc["paths"]["/health"]["get"];
+    assert_eq!("health", health_op["operationId"]);
+    assert_eq!(
+        json!({"200": {"description": "OK"}}),
+        health_op["responses"]
+    );
+
+    let resp = reqwest::Client::new()
+        .head("http://127.0.0.1:3000/openapi.json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(len, resp.headers().get(CONTENT_LENGTH).unwrap());
+    assert_eq!("", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        openapi: {
+            route: "/openapi.json",
+            title: "users",
+            version: "1.0.0"
+        },
+        handlers: [{
+            route: "/users/{id:u64}",
+            method: GET,
+            handler: get_user
+        },
+        {
+            route: "/users",
+            method: GET,
+            handler: list_users
+        },
+        {
+            route: "/users",
+            method: POST,
+            handler: create_user
+        },
+        {
+            route: "/health",
+            method: GET,
+            handler: health
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,89 @@
+use darpi::{app, handler, App, Path, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct File {
+    pub name: String,
+}
+
+#[handler]
+async fn get_file(#[path] f: File) -> String {
+    format!("file {}", f.name)
+}
+
+#[tokio::test]
+async fn path_normalization() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/files/report")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("file report", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/files/report/")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("file report", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000//files//report")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("file report", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/files/my%20report")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("file my report", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/files/")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        routing: {
+            trailing_slash: match,
+            percent_decode: true
+        },
+        handlers: [{
+            route: "/files/{name}",
+            method: GET,
+            handler: get_file
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
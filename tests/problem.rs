@@ -0,0 +1,238 @@
+use darpi::header::CONTENT_TYPE;
+use darpi::problem::{PlainText, Problem, ProblemJson};
+use darpi::response::ResponderError;
+use darpi::{app, handler, App, Json, Path, Query, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct UserId {
+    pub id: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Query)]
+pub struct Page {
+    pub page: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Name {
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct Teapot;
+
+impl fmt::Display for Teapot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "short and stout")
+    }
+}
+
+impl ResponderError for Teapot {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::IM_A_TEAPOT
+    }
+}
+
+#[handler]
+async fn user(#[path] p: UserId) -> Result<String, Problem> {
+    if p.id == 0 {
+        return Err(Problem::new(StatusCode::NOT_FOUND)
+            .problem_type("https://example.com/probs/unknown-user")
+            .title("Unknown user")
+            .detail(format!("user {} does not exist", p.id)));
+    }
+    Ok(format!("user {}", p.id))
+}
+
+#[handler]
+async fn users(#[query] p: Page) -> String {
+    format!("page {}", p.page)
+}
+
+#[handler]
+async fn hello(#[body] n: Json<Name>) -> String {
+    format!("hello {}", n.name)
+}
+
+#[handler]
+async fn brew() -> Result<String, Teapot> {
+    Err(Teapot)
+}
+
+#[tokio::test]
+async fn problem() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/users/1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("user 1", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/users/0")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    assert_eq!(
+        "application/problem+json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "type": "https://example.com/probs/unknown-user",
+            "title": "Unknown user",
+            "status": 404,
+            "detail": "user 0 does not exist"
+        }),
+        doc
+    );
+
+    // framework errors
+    let resp = client
+        .get("http://127.0.0.1:3000/users/abc")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    assert_eq!(
+        "application/problem+json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!("about:blank", doc["type"]);
+    assert_eq!("Bad Request", doc["title"]);
+    assert_eq!(400, doc["status"]);
+    assert!(doc["detail"].is_string());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/users")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!("missing query params", doc["detail"]);
+
+    let resp = client
+        .post("http://127.0.0.1:3000/hello")
+        .body("{\"name\": \"john\"}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "application/problem+json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    let status = resp.status();
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(status.as_u16(), doc["status"]);
+    assert_eq!("MissingContentType", doc["detail"]);
+
+    // user errors
+    let resp = client
+        .get("http://127.0.0.1:3000/brew")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::IM_A_TEAPOT, resp.status());
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "type": "about:blank",
+            "title": "I'm a teapot",
+            "status": 418,
+            "detail": "short and stout"
+        }),
+        doc
+    );
+
+    // every app keeps its own renderer
+    let mut plain = app!({
+        address: "127.0.0.1:3001",
+        error_renderer: PlainText,
+        handlers: [{
+            route: "/brew",
+            method: GET,
+            handler: brew
+        }]
+    });
+    let plain_shutdown = plain.shutdown_signal().unwrap();
+    let plain_startup = plain.startup_notify().unwrap();
+    tokio::spawn(async {
+        plain.run().await.unwrap();
+    });
+    plain_startup.await.unwrap();
+
+    let resp = client
+        .get("http://127.0.0.1:3001/brew")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "text/plain; charset=utf-8",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("short and stout", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/brew")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "application/problem+json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+
+    plain_shutdown.send(()).unwrap();
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        error_renderer: ProblemJson,
+        handlers: [{
+            route: "/users/{id}",
+            method: GET,
+            handler: user
+        },
+        {
+            route: "/users",
+            method: GET,
+            handler: users
+        },
+        {
+            route: "/hello",
+            method: POST,
+            handler: hello
+        },
+        {
+            route: "/brew",
+            method: GET,
+            handler: brew
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
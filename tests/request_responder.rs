@@ -0,0 +1,189 @@
+use darpi::cookie::{Cookie, SetCookie};
+use darpi::header::{ACCEPT, ACCEPT_LANGUAGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, SET_COOKIE};
+use darpi::response::RequestResponder;
+use darpi::{
+    app, handler, App, Body, Negotiated, Request, RequestParts, Responder, Response, StatusCode,
+};
+use darpi_files::{NamedFile, NamedFileErr};
+use env_logger;
+use futures::Future;
+use serde::Serialize;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+pub struct Greeting;
+
+impl RequestResponder for Greeting {
+    fn respond_to(self, parts: &RequestParts) -> Response<Body> {
+        match parts.headers.get(ACCEPT_LANGUAGE) {
+            Some(lang) if lang.as_bytes().starts_with(b"fr") => "bonjour".respond(),
+            _ => "hello".respond(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Count {
+    pub count: u32,
+}
+
+#[handler]
+async fn greet() -> Greeting {
+    Greeting
+}
+
+#[handler]
+async fn maybe_greet() -> Option<Greeting> {
+    None
+}
+
+#[handler]
+async fn try_greet(#[request] r: Request<Body>) -> Result<Greeting, String> {
+    if r.uri().query() == Some("fail") {
+        return Err("no greeting".to_string());
+    }
+    Ok(Greeting)
+}
+
+#[handler]
+async fn query(#[request] r: Request<Body>) -> impl Responder {
+    r.uri().query().unwrap_or_default().to_string()
+}
+
+#[handler]
+async fn count() -> impl RequestResponder {
+    Negotiated::new(Count { count: 1 }).cookie(Cookie::new("counted", "1"))
+}
+
+#[handler]
+async fn digits() -> Result<NamedFile, NamedFileErr> {
+    NamedFile::open("tests/static/digits.txt").await
+}
+
+#[tokio::test]
+async fn request_responder() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/greet")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("hello", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/greet")
+        .header(ACCEPT_LANGUAGE, "fr-CH, fr;q=0.9")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("bonjour", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/maybe_greet")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    // the request is consumed by the handler
+    let resp = client
+        .get("http://127.0.0.1:3000/try_greet")
+        .header(ACCEPT_LANGUAGE, "fr")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("bonjour", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/query?page=2")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("page=2", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/try_greet?fail")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+    assert_eq!("no greeting", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/count")
+        .header(ACCEPT, "application/yaml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "application/yaml",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("counted=1", resp.headers().get(SET_COOKIE).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/digits")
+        .send()
+        .await
+        .unwrap();
+    let etag = resp.headers().get(ETAG).unwrap().clone();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/digits")
+        .header(IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/greet",
+            method: GET,
+            handler: greet
+        },
+        {
+            route: "/maybe_greet",
+            method: GET,
+            handler: maybe_greet
+        },
+        {
+            route: "/try_greet",
+            method: GET,
+            handler: try_greet
+        },
+        {
+            route: "/query",
+            method: GET,
+            handler: query
+        },
+        {
+            route: "/count",
+            method: GET,
+            handler: count
+        },
+        {
+            route: "/digits",
+            method: GET,
+            handler: digits
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
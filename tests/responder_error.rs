@@ -0,0 +1,150 @@
+use darpi::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use darpi::problem::ProblemJson;
+use darpi::{app, handler, App, Path, ResponderError, StatusCode};
+use derive_more::Display;
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Debug, Display, ResponderError)]
+#[problem(type = "https://example.com/probs/account")]
+pub enum AccountError {
+    #[status(401)]
+    #[header("WWW-Authenticate", "Bearer")]
+    #[display(fmt = "missing token")]
+    MissingToken,
+    #[status(403)]
+    #[problem(title = "Forbidden account")]
+    #[display(fmt = "user {} lacks the {} role", user, role)]
+    Forbidden { user: String, role: String },
+    #[status(500)]
+    #[message("the account could not be loaded")]
+    #[display(fmt = "db connection refused on {}", _0)]
+    Database(String),
+}
+
+#[derive(Debug, Display, ResponderError)]
+#[status(409)]
+#[display(fmt = "already exists")]
+pub struct Conflict;
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct Kind {
+    pub kind: String,
+}
+
+#[handler]
+async fn account(#[path] p: Kind) -> Result<String, AccountError> {
+    match p.kind.as_str() {
+        "anonymous" => Err(AccountError::MissingToken),
+        "guest" => Err(AccountError::Forbidden {
+            user: "guest".to_string(),
+            role: "admin".to_string(),
+        }),
+        "broken" => Err(AccountError::Database("10.0.0.1:5432".to_string())),
+        _ => Ok("account".to_string()),
+    }
+}
+
+#[handler]
+async fn create() -> Result<String, Conflict> {
+    Err(Conflict)
+}
+
+#[tokio::test]
+async fn responder_error() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/account/anonymous")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+    assert_eq!("Bearer", resp.headers().get(WWW_AUTHENTICATE).unwrap());
+    assert_eq!(
+        "application/problem+json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "type": "https://example.com/probs/account",
+            "title": "Unauthorized",
+            "status": 401,
+            "detail": "missing token"
+        }),
+        doc
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/account/guest")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, resp.status());
+    assert!(resp.headers().get(WWW_AUTHENTICATE).is_none());
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!("Forbidden account", doc["title"]);
+    assert_eq!("user guest lacks the admin role", doc["detail"]);
+
+    // the internal detail is logged, not sent
+    let resp = client
+        .get("http://127.0.0.1:3000/account/broken")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!("the account could not be loaded", doc["detail"]);
+
+    let resp = client
+        .get("http://127.0.0.1:3000/account/me")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    let resp = client
+        .post("http://127.0.0.1:3000/create")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::CONFLICT, resp.status());
+    let doc: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!("about:blank", doc["type"]);
+    assert_eq!("already exists", doc["detail"]);
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        error_renderer: ProblemJson,
+        handlers: [{
+            route: "/account/{kind}",
+            method: GET,
+            handler: account
+        },
+        {
+            route: "/create",
+            method: POST,
+            handler: create
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,92 @@
+use darpi::{app, handler, App, Path, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct UserId {
+    pub id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct Slug {
+    pub slug: String,
+}
+
+#[handler]
+async fn user_by_id(#[path] p: UserId) -> String {
+    format!("user {}", p.id)
+}
+
+#[handler]
+async fn me() -> &'static str {
+    "me"
+}
+
+#[handler]
+async fn user_by_slug(#[path] p: Slug) -> String {
+    format!("slug {}", p.slug)
+}
+
+#[tokio::test]
+async fn route_constraints() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = reqwest::get("http://127.0.0.1:3000/users/42")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("user 42", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/users/me")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("me", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/users/hello-world")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("slug hello-world", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/users/Hello")
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/users/{id:u64}",
+            method: GET,
+            handler: user_by_id
+        },
+        {
+            route: "/users/me",
+            method: GET,
+            handler: me
+        },
+        {
+            route: "/users/{slug:[a-z-]+}",
+            method: GET,
+            handler: user_by_slug
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
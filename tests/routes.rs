@@ -0,0 +1,97 @@
+use darpi::response::ResponderError;
+use darpi::{app, handler, middleware, App, Body, Path, Request, Response, RouteInfo};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+
+#[derive(Display)]
+#[display(fmt = "missing api key")]
+pub struct Unauthorized;
+
+impl ResponderError for Unauthorized {}
+
+#[middleware(Request)]
+async fn api_key(#[request] r: &Request<Body>) -> Result<(), Unauthorized> {
+    if r.headers().contains_key("x-api-key") {
+        return Ok(());
+    }
+    Err(Unauthorized)
+}
+
+#[middleware(Response)]
+async fn trace(
+    #[response] _r: &mut Response<Body>,
+    #[handler] _name: &'static str,
+) -> Result<(), Infallible> {
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct Item {
+    pub id: u64,
+    pub slug: String,
+}
+
+#[handler]
+async fn home() -> &'static str {
+    "home"
+}
+
+#[handler]
+async fn get_item(#[path] i: Item) -> String {
+    format!("{} {}", i.id, i.slug)
+}
+
+#[test]
+fn routes() {
+    let app = app!({
+        address: "127.0.0.1:3000",
+        middleware: {
+            response: [trace("app")]
+        },
+        handlers: [{
+            route: "/",
+            method: GET,
+            handler: home,
+            name: "home"
+        },
+        {
+            scope: "/api",
+            middleware: {
+                request: [api_key],
+                response: [trace("api")]
+            },
+            handlers: [{
+                route: "/items/{id:u64}/{slug}",
+                method: GET,
+                handler: get_item
+            }]
+        }]
+    });
+
+    let routes = app.routes();
+    assert_eq!(
+        vec![
+            RouteInfo {
+                route: "/",
+                method: "GET",
+                handler: "home",
+                name: Some("home"),
+                args: vec![],
+                request_middleware: vec![],
+                response_middleware: vec!["trace"],
+            },
+            RouteInfo {
+                route: "/api/items/{id:u64}/{slug}",
+                method: "GET",
+                handler: "get_item",
+                name: None,
+                args: vec!["id", "slug"],
+                request_middleware: vec!["api_key"],
+                response_middleware: vec!["trace", "trace"],
+            }
+        ],
+        routes
+    );
+    assert_eq!("GET / -> home (home)", routes[0].to_string());
+}
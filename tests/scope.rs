@@ -0,0 +1,133 @@
+use darpi::header::HeaderValue;
+use darpi::response::ResponderError;
+use darpi::{app, handler, middleware, App, Body, Request, Response, StatusCode};
+use derive_more::Display;
+use env_logger;
+use futures::Future;
+use std::convert::Infallible;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Display)]
+#[display(fmt = "missing api key")]
+pub struct Unauthorized;
+
+impl ResponderError for Unauthorized {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+}
+
+#[middleware(Request)]
+async fn api_key(#[request] r: &Request<Body>) -> Result<(), Unauthorized> {
+    if r.headers().contains_key("x-api-key") {
+        return Ok(());
+    }
+    Err(Unauthorized)
+}
+
+#[middleware(Response)]
+async fn trace(
+    #[response] r: &mut Response<Body>,
+    #[handler] name: &'static str,
+) -> Result<(), Infallible> {
+    let value = match r.headers().get("x-trace") {
+        Some(v) => format!("{},{}", v.to_str().unwrap(), name),
+        None => name.to_string(),
+    };
+    r.headers_mut()
+        .insert("x-trace", HeaderValue::from_str(&value).unwrap());
+    Ok(())
+}
+
+#[handler]
+async fn hello_world_handler() -> &'static str {
+    "hello world"
+}
+
+#[tokio::test]
+async fn scope() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/hello_world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert!(resp.headers().get("x-trace").is_none());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/api/v1/hello_world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/api/v1/hello_world")
+        .header("x-api-key", "secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("v1,api", resp.headers().get("x-trace").unwrap());
+    assert_eq!("hello world", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/api/hello_world")
+        .header("x-api-key", "secret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("api", resp.headers().get("x-trace").unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/hello_world",
+            method: GET,
+            handler: hello_world_handler
+        },
+        {
+            scope: "/api",
+            middleware: {
+                request: [api_key],
+                response: [trace("api")]
+            },
+            handlers: [{
+                route: "/hello_world",
+                method: GET,
+                handler: hello_world_handler
+            },
+            {
+                scope: "/v1",
+                middleware: {
+                    response: [trace("v1")]
+                },
+                handlers: [{
+                    route: "/hello_world",
+                    method: GET,
+                    handler: hello_world_handler
+                }]
+            }]
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
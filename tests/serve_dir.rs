@@ -0,0 +1,237 @@
+use darpi::header::{
+    ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE,
+};
+use darpi::{app, handler, App, Path, StatusCode};
+use darpi_files::serve_dir;
+use env_logger;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Deserialize, Serialize, Debug, Path)]
+pub struct Rest {
+    pub path: String,
+}
+
+#[handler]
+async fn echo(#[path] p: Rest) -> String {
+    p.path
+}
+
+#[tokio::test]
+async fn serve_dir_test() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    // `{*path}` captures the rest of the path
+    let resp = client
+        .get("http://127.0.0.1:3000/echo/a/b/c")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("a/b/c", resp.text().await.unwrap());
+    assert_eq!(
+        "/echo/a%20b/c",
+        echo::echo_url(&Rest {
+            path: "a b/c".to_string()
+        })
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "text/plain; charset=utf-8",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("bytes", resp.headers().get("accept-ranges").unwrap());
+    let etag = resp.headers().get(ETAG).unwrap().clone();
+    let last_modified = resp.headers().get(LAST_MODIFIED).unwrap().clone();
+    assert_eq!("0123456789", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/hello%20world.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("hello world\n", resp.text().await.unwrap());
+
+    // conditional GET
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(IF_NONE_MATCH, etag.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+    assert_eq!(&etag, resp.headers().get(ETAG).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(IF_MODIFIED_SINCE, last_modified.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(IF_NONE_MATCH, "\"other\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+
+    // ranges
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(RANGE, "bytes=2-4")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+    assert_eq!("bytes 2-4/10", resp.headers().get(CONTENT_RANGE).unwrap());
+    assert_eq!("234", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(RANGE, "bytes=-3")
+        .header(IF_RANGE, etag.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+    assert_eq!("789", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(RANGE, "bytes=5-")
+        .header(IF_RANGE, "\"stale\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("0123456789", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/digits.txt")
+        .header(RANGE, "bytes=10-")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, resp.status());
+    assert_eq!("bytes */10", resp.headers().get(CONTENT_RANGE).unwrap());
+
+    // precompressed sidecars
+    let resp = client
+        .get("http://127.0.0.1:3000/static/app.js")
+        .header(ACCEPT_ENCODING, "br;q=0.5, gzip;q=0.8")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("gzip", resp.headers().get(CONTENT_ENCODING).unwrap());
+    assert_eq!(
+        "text/javascript; charset=utf-8",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    let gz = std::fs::read("tests/static/app.js.gz").unwrap();
+    assert_eq!(gz, resp.bytes().await.unwrap().to_vec());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/app.js")
+        .header(ACCEPT_ENCODING, "identity")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+    assert_eq!("console.log(\"darpi\");\n", resp.text().await.unwrap());
+
+    // directories
+    let resp = client
+        .get("http://127.0.0.1:3000/static/docs")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, resp.status());
+    assert_eq!("/static/docs/", resp.headers().get(LOCATION).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/docs/")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "text/html; charset=utf-8",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("<h1>docs</h1>\n", resp.text().await.unwrap());
+
+    // path traversal
+    let resp = client
+        .get("http://127.0.0.1:3000/static/..%2fserve_dir.rs")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/docs/..%2F..%2Fserve_dir.rs")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/static/missing.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    let resp = client
+        .head("http://127.0.0.1:3000/static/digits.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("10", resp.headers().get("content-length").unwrap());
+    assert_eq!("", resp.text().await.unwrap());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/static/{*path}",
+            method: GET,
+            handler: serve_dir("tests/static")
+        },
+        {
+            route: "/echo/{*path}",
+            method: GET,
+            handler: echo,
+            name: "echo"
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
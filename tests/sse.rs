@@ -0,0 +1,148 @@
+use darpi::header::{CACHE_CONTROL, CONTENT_TYPE};
+use darpi::job::FutureJob;
+use darpi::sse::{Event, LAST_EVENT_ID};
+use darpi::{app, handler, stream, App, RequestParts, Sse, StatusCode};
+use env_logger;
+use futures::{Future, StreamExt};
+use std::time::Duration;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn ticks() -> Sse {
+    Sse::resume(|last_event_id| {
+        let from = last_event_id
+            .and_then(|id| id.parse().ok())
+            .map_or(0, |id: u32| id + 1);
+        futures::stream::iter(from..3).map(|i| Event::data(format!("tick {}", i)).id(i.to_string()))
+    })
+}
+
+#[handler]
+async fn parts_ticks(#[request_parts] rp: &RequestParts) -> Sse {
+    let from = Sse::last_event_id(&rp.headers)
+        .and_then(|id| id.parse().ok())
+        .map_or(0, |id: u32| id + 1);
+    let events = futures::stream::iter(from..3)
+        .map(|i| Event::data(format!("tick {}", i)).id(i.to_string()));
+    Sse::new(events)
+}
+
+#[handler]
+async fn fields() -> Sse {
+    let event = Event::data("first\nsecond")
+        .event("update")
+        .id("7\n")
+        .retry(Duration::from_millis(1500));
+    Sse::new(futures::stream::iter(vec![event]))
+}
+
+#[handler]
+async fn idle() -> Sse {
+    let (tx, rx) = stream::channel(1);
+    darpi::spawn(FutureJob::from(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let _ = tx.send(Event::data("done")).await;
+    }))
+    .unwrap();
+    Sse::new(rx).keep_alive(Duration::from_millis(50))
+}
+
+#[tokio::test]
+async fn sse() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/ticks")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "text/event-stream",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("no-cache", resp.headers().get(CACHE_CONTROL).unwrap());
+    assert_eq!(
+        "id: 0\ndata: tick 0\n\nid: 1\ndata: tick 1\n\nid: 2\ndata: tick 2\n\n",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/ticks")
+        .header(LAST_EVENT_ID, "1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("id: 2\ndata: tick 2\n\n", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/parts_ticks")
+        .header(LAST_EVENT_ID, "0")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "id: 1\ndata: tick 1\n\nid: 2\ndata: tick 2\n\n",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/fields")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        "id: 7\nevent: update\nretry: 1500\ndata: first\ndata: second\n\n",
+        resp.text().await.unwrap()
+    );
+
+    let resp = client
+        .get("http://127.0.0.1:3000/idle")
+        .send()
+        .await
+        .unwrap();
+    let body = resp.text().await.unwrap();
+    assert!(body.starts_with(": keep-alive\n\n"));
+    assert!(body.ends_with("data: done\n\n"));
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/ticks",
+            method: GET,
+            handler: ticks
+        },
+        {
+            route: "/parts_ticks",
+            method: GET,
+            handler: parts_ticks
+        },
+        {
+            route: "/fields",
+            method: GET,
+            handler: fields
+        },
+        {
+            route: "/idle",
+            method: GET,
+            handler: idle
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,141 @@
+use darpi::header::CONTENT_TYPE;
+use darpi::job::FutureJob;
+use darpi::stream::{self, Stream};
+use darpi::{app, handler, App, StatusCode};
+use env_logger;
+use futures::Future;
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[derive(Serialize)]
+struct Row {
+    id: u32,
+}
+
+#[handler]
+async fn raw() -> Stream {
+    let chunks = vec!["hello", " ", "world"]
+        .into_iter()
+        .map(Ok::<_, Infallible>);
+    Stream::bytes(futures::stream::iter(chunks))
+}
+
+#[handler]
+async fn ndjson() -> Stream {
+    let rows = (0..3).map(|id| Row { id });
+    Stream::ndjson(futures::stream::iter(rows))
+}
+
+#[handler]
+async fn array() -> Stream {
+    let rows = (0..3).map(|id| Row { id });
+    Stream::json_array(futures::stream::iter(rows))
+}
+
+#[handler]
+async fn empty() -> Stream {
+    Stream::json_array(futures::stream::empty::<Row>())
+}
+
+#[handler]
+async fn produced() -> Stream {
+    let (tx, rx) = stream::channel(2);
+    darpi::spawn(FutureJob::from(async move {
+        for id in 0..100 {
+            if tx.send(Row { id }).await.is_err() {
+                return;
+            }
+        }
+    }))
+    .unwrap();
+    Stream::ndjson(rx)
+}
+
+#[tokio::test]
+async fn stream_response() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = reqwest::get("http://127.0.0.1:3000/raw").await.unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!(
+        "application/octet-stream",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!("hello world", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/ndjson").await.unwrap();
+    assert_eq!(
+        "application/x-ndjson",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!(
+        "{\"id\":0}\n{\"id\":1}\n{\"id\":2}\n",
+        resp.text().await.unwrap()
+    );
+
+    let resp = reqwest::get("http://127.0.0.1:3000/array").await.unwrap();
+    assert_eq!(
+        "application/json",
+        resp.headers().get(CONTENT_TYPE).unwrap()
+    );
+    assert_eq!(
+        "[{\"id\":0},{\"id\":1},{\"id\":2}]",
+        resp.text().await.unwrap()
+    );
+
+    let resp = reqwest::get("http://127.0.0.1:3000/empty").await.unwrap();
+    assert_eq!("[]", resp.text().await.unwrap());
+
+    let resp = reqwest::get("http://127.0.0.1:3000/produced")
+        .await
+        .unwrap();
+    let body = resp.text().await.unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(100, lines.len());
+    assert_eq!("{\"id\":99}", lines[99]);
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        handlers: [{
+            route: "/raw",
+            method: GET,
+            handler: raw
+        },
+        {
+            route: "/ndjson",
+            method: GET,
+            handler: ndjson
+        },
+        {
+            route: "/array",
+            method: GET,
+            handler: array
+        },
+        {
+            route: "/empty",
+            method: GET,
+            handler: empty
+        },
+        {
+            route: "/produced",
+            method: GET,
+            handler: produced
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,90 @@
+use darpi::{app, handler, App};
+use env_logger;
+use futures::Future;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys};
+use rustls::ClientConfig;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot::{Receiver, Sender};
+use tokio_rustls::webpki::DNSNameRef;
+use tokio_rustls::TlsConnector;
+
+#[handler]
+async fn hello_world_handler() -> &'static str {
+    "hello world"
+}
+
+#[tokio::test]
+async fn tls() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let resp = get(client_config(true)).await.unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK"));
+    assert!(resp.ends_with("hello world"));
+
+    // the server requires a client certificate signed by tests/tls/ca.pem
+    assert!(get(client_config(false)).await.is_err());
+    shutdown.send(()).unwrap();
+}
+
+async fn get(config: ClientConfig) -> io::Result<String> {
+    let connector = TlsConnector::from(Arc::new(config));
+    let stream = TcpStream::connect("127.0.0.1:3000").await?;
+    let domain = DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let mut stream = connector.connect(domain, stream).await?;
+
+    stream
+        .write_all(b"GET /hello_world HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).await?;
+    Ok(resp)
+}
+
+fn client_config(with_cert: bool) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    let mut ca = BufReader::new(File::open("tests/tls/ca.pem").unwrap());
+    config.root_store.add_pem_file(&mut ca).unwrap();
+
+    if with_cert {
+        let mut cert = BufReader::new(File::open("tests/tls/client_cert.pem").unwrap());
+        let mut key = BufReader::new(File::open("tests/tls/client_key.pem").unwrap());
+        let cert = certs(&mut cert).unwrap();
+        let key = pkcs8_private_keys(&mut key).unwrap().remove(0);
+        config.set_single_client_cert(cert, key).unwrap();
+    }
+    config
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        tls: {
+            cert: "tests/tls/cert.pem",
+            key: "tests/tls/key.pem",
+            client_ca: "tests/tls/ca.pem",
+            client_auth: required,
+            reload: 60,
+        },
+        handlers: [{
+            route: "/hello_world",
+            method: GET,
+            handler: hello_world_handler
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}
@@ -0,0 +1,97 @@
+use darpi::header::LOCATION;
+use darpi::{app, handler, App, StatusCode};
+use env_logger;
+use futures::Future;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+#[handler]
+async fn users() -> &'static str {
+    "users"
+}
+
+#[handler]
+async fn settings() -> &'static str {
+    "settings"
+}
+
+#[tokio::test]
+async fn trailing_slash_redirect() {
+    let (shutdown, startup, app) = make_server();
+    tokio::spawn(app);
+    startup.await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let resp = client
+        .get("http://127.0.0.1:3000/users")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("users", resp.text().await.unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/users/?page=2")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, resp.status());
+    assert_eq!("/users?page=2", resp.headers().get(LOCATION).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/settings")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, resp.status());
+    assert_eq!("/settings/", resp.headers().get(LOCATION).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000//users")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, resp.status());
+    assert_eq!("/users", resp.headers().get(LOCATION).unwrap());
+
+    let resp = client
+        .get("http://127.0.0.1:3000/unknown/")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    assert!(resp.headers().get(LOCATION).is_none());
+
+    shutdown.send(()).unwrap();
+}
+
+fn make_server() -> (Sender<()>, Receiver<()>, impl Future<Output = ()>) {
+    env_logger::builder().is_test(true).init();
+
+    let mut app = app!({
+        address: "127.0.0.1:3000",
+        routing: {
+            trailing_slash: redirect
+        },
+        handlers: [{
+            route: "/users",
+            method: GET,
+            handler: users
+        },
+        {
+            route: "/settings/",
+            method: GET,
+            handler: settings
+        }]
+    });
+
+    let shutdown = app.shutdown_signal().unwrap();
+    let startup = app.startup_notify().unwrap();
+
+    (shutdown, startup, async {
+        app.run().await.unwrap();
+    })
+}